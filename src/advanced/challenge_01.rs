@@ -1,8 +1,10 @@
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq)]
+/// JSON: `{"CounterIncremented":{"new_value":1}}` or `"CounterReset"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Event {
     /// Counter was incremented. [new_value]
     CounterIncremented { new_value: u32 },
@@ -12,7 +14,7 @@ pub enum Event {
     CounterReset,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Error {
     /// Cannot decrement counter below zero
     CounterUnderflow,
@@ -261,9 +263,15 @@ pub struct TestEvent(Event);
         assert_eq!(pallet.get_events().len(), 0); // No events on failure
     }
 
-}
-
-
-
-
+    #[test]
+    fn events_json_round_trip() {
+        let mut pallet = TestPallet::new();
+        assert!(pallet.increment().is_ok());
+        assert!(pallet.reset().is_ok());
+        let events: Vec<Event> = pallet.get_events().iter().map(|event| event.0.clone()).collect();
 
+        let json = serde_json::to_string(&events).unwrap();
+        assert_eq!(json, r#"[{"CounterIncremented":{"new_value":1}},"CounterReset"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Event>>(&json).unwrap(), events);
+    }
+}
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::marker::PhantomData;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// JSON: `{"ref_time":25000,"proof_size":1024}`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Weight {
     pub ref_time: u64,
     pub proof_size: u64,
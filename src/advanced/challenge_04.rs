@@ -1,4 +1,7 @@
-#[derive(Debug, Clone,PartialEq)]
+use serde::{Deserialize, Serialize};
+
+/// JSON: `"ItemNotFound"` or `{"InvalidParams":"Missing ID"}`.
+#[derive(Debug, Clone,PartialEq, Serialize, Deserialize)]
 pub enum RpcError {
     ItemNotFound,
     InternalError(String),
@@ -162,13 +165,17 @@ impl<T: CustomRpc> RpcServer<T> {
 }
 
 /// RPC request parameters
-#[derive(Debug, Clone)]
+///
+/// JSON: `{"id":1}` or `{"id":null}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RpcParams {
     pub id: Option<u32>,
 }
 
 /// RPC response data
-#[derive(Debug, PartialEq)]
+///
+/// JSON: externally tagged, e.g. `{"Item":"First item"}` or `{"Items":[[1,"First item"]]}`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum ResponseData {
     Item(String),
     Items(Vec<(u32, String)>),
@@ -177,7 +184,9 @@ pub enum ResponseData {
 }
 
 /// RPC response
-#[derive(Debug, PartialEq)]
+///
+/// JSON: `{"success":true,"data":{"Count":3},"error":null}`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct RpcResponse {
     pub success: bool,
     pub data: Option<ResponseData>,
@@ -352,4 +361,26 @@ mod tests {
             RpcError::InternalError("Too many items".to_string())
         );
     }
+
+    #[test]
+    fn rpc_response_json_round_trip() {
+        let server = create_server();
+        let response = server.handle_request("get_items_count", RpcParams { id: None });
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(json, r#"{"success":true,"data":{"Count":3},"error":null}"#);
+        assert_eq!(serde_json::from_str::<RpcResponse>(&json).unwrap(), response);
+
+        let response = server.handle_request("get_item", RpcParams { id: None });
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(json, r#"{"success":false,"data":null,"error":{"InvalidParams":"Missing ID"}}"#);
+        assert_eq!(serde_json::from_str::<RpcResponse>(&json).unwrap(), response);
+    }
+
+    #[test]
+    fn rpc_params_from_json_fixture() {
+        let server = create_server();
+        let params: RpcParams = serde_json::from_str(r#"{"id":2}"#).unwrap();
+        let response = server.handle_request("get_item", params);
+        assert_eq!(response.data, Some(ResponseData::Item("Second item".to_string())));
+    }
 }
@@ -1,9 +1,37 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Formatter;
+use crate::common::codec::Encode;
+use crate::common::signature::{Keypair, PublicKey, Signature};
+
+/// Priority unsigned transactions start from, high enough to rank them before most signed ones.
+pub const DEFAULT_UNSIGNED_PRIORITY: u64 = 1 << 20;
+/// Blocks an unsigned transaction stays valid for after the block it was created at.
+pub const DEFAULT_UNSIGNED_LONGEVITY: u64 = 64;
+
+pub type Tag = Vec<u8>;
+
+/// Tag an unsigned transaction provides, so no two with the same nonce are pooled together.
+pub fn unsigned_tag(nonce: u64) -> Tag {
+    [b"unsigned:".as_slice(), &nonce.to_le_bytes()].concat()
+}
+
+/// How a transaction pool should treat a valid unsigned transaction, as in Substrate's
+/// `ValidateUnsigned`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidTransaction {
+    /// Higher is included first.
+    pub priority: u64,
+    /// Blocks from now after which the transaction is dropped.
+    pub longevity: u64,
+    /// Whether it should be gossiped to other nodes or kept local.
+    pub propagate: bool,
+    pub provides: Vec<Tag>,
+    pub requires: Vec<Tag>,
+}
 
 #[derive(Debug, PartialEq)]
 pub enum ValidationResult {
-    Valid,
+    Valid(ValidTransaction),
     Invalid(ValidationError)
 }
 
@@ -13,6 +41,16 @@ pub enum ValidationError {
     TooEarly,
     InvalidData(String),
     Duplicate,
+    /// All sources together used up the current interval.
+    GlobalLimitReached,
+    /// Signed by a key outside the current authority set.
+    UnknownAuthority,
+    /// Missing or not matching the payload, while an authority set is configured.
+    BadSignature,
+    /// The source is not the signer's index in the current authority set.
+    SourceMismatch,
+    /// Created more than the validator's longevity ago.
+    Stale,
 }
 
 
@@ -22,7 +60,12 @@ impl std::fmt::Display for ValidationError {
             ValidationError::TooManyTransactions => write!(f, "Too many transactions in current interval"),
             ValidationError::TooEarly => write!(f, "Transaction submitted too early"),
             ValidationError::InvalidData(msg) => write!(f, "invalid Data: {}", msg),
-            ValidationError::Duplicate => write!(f, "Duplicate transaction")
+            ValidationError::Duplicate => write!(f, "Duplicate transaction"),
+            ValidationError::GlobalLimitReached => write!(f, "Too many transactions from all sources in current interval"),
+            ValidationError::Stale => write!(f, "Transaction is outdated"),
+            ValidationError::UnknownAuthority => write!(f, "Signer is not a current authority"),
+            ValidationError::BadSignature => write!(f, "Invalid payload signature"),
+            ValidationError::SourceMismatch => write!(f, "Source does not match the signing authority"),
 
 
         }
@@ -64,11 +107,20 @@ impl BlockSimulator {
 
 }
 
+/// Identifies who submitted an unsigned transaction, e.g. an authority's index.
+pub type SourceId = u32;
+
+/// Source of transactions built without `with_source`.
+pub const DEFAULT_SOURCE: SourceId = 0;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UnsignedTransaction<T> {
     pub data: T,
     pub block_number: u64,
-    pub nonce: u64
+    pub nonce: u64,
+    pub source: SourceId,
+    /// Signer and signature of `signing_payload`, for validators with an authority set.
+    pub signature: Option<(PublicKey, Signature)>,
 }
 
 impl <T> UnsignedTransaction<T> {
@@ -76,23 +128,74 @@ impl <T> UnsignedTransaction<T> {
         Self {
             data,
             block_number,
-            nonce
+            nonce,
+            source: DEFAULT_SOURCE,
+            signature: None,
         }
     }
 
+    pub fn with_source(mut self, source: SourceId) -> Self {
+        self.source = source;
+        self
+    }
+
+}
+
+impl <T: Encode> UnsignedTransaction<T> {
+    /// Encoding of every field except the signature.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut payload = self.data.encode();
+        self.block_number.encode_to(&mut payload);
+        self.nonce.encode_to(&mut payload);
+        self.source.encode_to(&mut payload);
+        payload
+    }
+
+    /// Turns the transaction into a signed payload from `keypair`.
+    pub fn signed(mut self, keypair: &Keypair) -> Self {
+        self.signature = Some((keypair.public(), keypair.sign(&self.signing_payload())));
+        self
+    }
+}
+
+/// Rate limits applied to each source separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLimits {
+    pub max_per_interval: u32,
+    /// Blocks a source has to wait between two accepted transactions.
+    pub min_block_interval: u64,
+}
+
+impl SourceLimits {
+    pub fn new(max_per_interval: u32, min_block_interval: u64) -> Self {
+        Self { max_per_interval, min_block_interval }
+    }
 }
 
 pub struct TransactionValidator {
-    max_per_interval: u32,
+    /// Limits of sources without an entry in `source_limits`.
+    default_limits: SourceLimits,
+    source_limits: HashMap<SourceId, SourceLimits>,
+    /// Cap on the transactions of all sources together per interval.
+    global_max_per_interval: Option<u32>,
     interval_blocks: u64,
-    min_block_interval: u64,
+    /// Transactions accepted from all sources, by interval start.
     interval_counts: HashMap<u64, u32>,
-    last_submission: Option<u64>,
+    source_interval_counts: HashMap<(SourceId, u64), u32>,
+    last_submissions: HashMap<SourceId, u64>,
+    /// Authority sets by the block they take effect at; an authority's index is its source.
+    /// Before the first one, transactions need no signature.
+    authority_sets: BTreeMap<u64, Vec<PublicKey>>,
     used_nonces: HashSet<u64>,
-    block_simulator: BlockSimulator
+    block_simulator: BlockSimulator,
+    priority: u64,
+    longevity: u64,
+    propagate: bool,
 }
 
 impl TransactionValidator {
+    /// `max_per_interval` and `min_block_interval` limit every source on its own; see
+    /// `with_source_limits` and `with_global_limit` for other limits.
     pub fn new(
         max_per_interval: u32,
         interval_blocks: u64,
@@ -100,26 +203,102 @@ impl TransactionValidator {
         block_simulator: BlockSimulator,
     ) -> Self {
         Self {
-            max_per_interval,
+            default_limits: SourceLimits::new(max_per_interval, min_block_interval),
+            source_limits: HashMap::new(),
+            global_max_per_interval: None,
             interval_blocks,
-            min_block_interval,
             interval_counts: HashMap::new(),
-            last_submission: None,
+            source_interval_counts: HashMap::new(),
+            last_submissions: HashMap::new(),
+            authority_sets: BTreeMap::new(),
             used_nonces: HashSet::new(),
             block_simulator,
+            priority: DEFAULT_UNSIGNED_PRIORITY,
+            longevity: DEFAULT_UNSIGNED_LONGEVITY,
+            propagate: true,
         }
     }
 
+    /// Priority of a transaction validated in the block it was created at; it drops by one
+    /// for every block since.
+    pub fn with_priority(mut self, priority: u64) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_longevity(mut self, longevity: u64) -> Self {
+        self.longevity = longevity;
+        self
+    }
+
+    pub fn with_propagate(mut self, propagate: bool) -> Self {
+        self.propagate = propagate;
+        self
+    }
+
+    pub fn with_source_limits(mut self, source: SourceId, limits: SourceLimits) -> Self {
+        self.source_limits.insert(source, limits);
+        self
+    }
+
+    pub fn with_global_limit(mut self, max_per_interval: u32) -> Self {
+        self.global_max_per_interval = Some(max_per_interval);
+        self
+    }
+
+    /// Requires every transaction to be a payload signed by one of `authorities`, with the
+    /// signer's position in `authorities` as its source.
+    pub fn with_authorities(mut self, authorities: impl IntoIterator<Item = PublicKey>) -> Self {
+        self.rotate_authorities(0, authorities);
+        self
+    }
+
+    /// Replaces the authority set from block `at_block` on.
+    pub fn rotate_authorities(&mut self, at_block: u64, authorities: impl IntoIterator<Item = PublicKey>) {
+        self.authority_sets.insert(at_block, authorities.into_iter().collect());
+    }
+
+    /// The authority set in effect at the current block, if any.
+    pub fn current_authorities(&self) -> Option<&[PublicKey]> {
+        let current_block = self.block_simulator.current_block();
+        self.authority_sets.range(..=current_block).next_back().map(|(_, authorities)| authorities.as_slice())
+    }
+
+    pub fn source_limits(&self, source: SourceId) -> SourceLimits {
+        self.source_limits.get(&source).copied().unwrap_or(self.default_limits)
+    }
+
     pub fn validate_transaction<T>
     (&self, transaction: &UnsignedTransaction<T>) -> ValidationResult
     where
-        T: std::fmt::Debug
+        T: std::fmt::Debug + Encode
     {
         let current_block = self.block_simulator.current_block();
+        let age = self.block_simulator.blocks_since(transaction.block_number);
+        if age >= self.longevity {
+            return ValidationResult::Invalid(ValidationError::Stale);
+        }
 
-        if let Some(last_block) = self.last_submission {
+        if let Some(authorities) = self.current_authorities() {
+            let Some((signer, signature)) = &transaction.signature else {
+                return ValidationResult::Invalid(ValidationError::BadSignature);
+            };
+            let Some(index) = authorities.iter().position(|authority| authority == signer) else {
+                return ValidationResult::Invalid(ValidationError::UnknownAuthority);
+            };
+            if !signer.verify(&transaction.signing_payload(), signature) {
+                return ValidationResult::Invalid(ValidationError::BadSignature);
+            }
+            // Otherwise an authority could spread its submissions over other sources' limits.
+            if SourceId::try_from(index) != Ok(transaction.source) {
+                return ValidationResult::Invalid(ValidationError::SourceMismatch);
+            }
+        }
+
+        let limits = self.source_limits(transaction.source);
+        if let Some(&last_block) = self.last_submissions.get(&transaction.source) {
             let blocks_since = self.block_simulator.blocks_since(last_block);
-            if blocks_since < self.min_block_interval {
+            if blocks_since < limits.min_block_interval {
                 return ValidationResult::Invalid(ValidationError::TooEarly);
             }
         }
@@ -129,33 +308,44 @@ impl TransactionValidator {
         }
 
         let interval_start = self.get_interval_start(current_block);
-        let current_count = self.interval_counts.get(&interval_start).unwrap_or(&0);
-
-        if *current_count >= self.max_per_interval {
+        let source_count = self.source_interval_counts.get(&(transaction.source, interval_start)).unwrap_or(&0);
+        if *source_count >= limits.max_per_interval {
             return ValidationResult::Invalid(ValidationError::TooManyTransactions);
         }
 
-        ValidationResult::Valid
+        let total_count = self.interval_counts.get(&interval_start).unwrap_or(&0);
+        if self.global_max_per_interval.is_some_and(|max| *total_count >= max) {
+            return ValidationResult::Invalid(ValidationError::GlobalLimitReached);
+        }
+
+        ValidationResult::Valid(ValidTransaction {
+            priority: self.priority.saturating_sub(age),
+            longevity: self.longevity - age,
+            propagate: self.propagate,
+            provides: vec![unsigned_tag(transaction.nonce)],
+            requires: Vec::new(),
+        })
     }
 
-    pub fn accept_transaction<T>(&mut self, transaction: &UnsignedTransaction<T>) -> Result<(), ValidationError>
+    pub fn accept_transaction<T>(&mut self, transaction: &UnsignedTransaction<T>) -> Result<ValidTransaction, ValidationError>
     where
-        T: std::fmt::Debug,
+        T: std::fmt::Debug + Encode,
 
     {
         let current_block = self.block_simulator.current_block();
-        match self.validate_transaction(transaction) {
-            ValidationResult::Valid => {},
+        let validity = match self.validate_transaction(transaction) {
+            ValidationResult::Valid(validity) => validity,
             ValidationResult::Invalid(error) => return Err(error)
-        }
+        };
 
         let interval_start = self.get_interval_start(current_block);
         let count = self.interval_counts.entry(interval_start).or_insert(0);
         *count += 1;
+        *self.source_interval_counts.entry((transaction.source, interval_start)).or_insert(0) += 1;
 
-        self.last_submission = Some(current_block);
+        self.last_submissions.insert(transaction.source, current_block);
         self.used_nonces.insert(transaction.nonce);
-        Ok(())
+        Ok(validity)
     }
 
     fn get_interval_start(&self, block_number: u64) -> u64 {
@@ -168,18 +358,38 @@ impl TransactionValidator {
         let current_interval_start = self.get_interval_start(current_block);
         let cutoff = current_interval_start.saturating_sub(self.interval_blocks);
         self.interval_counts.retain(|&interval_start, _| interval_start >= cutoff);
+        self.source_interval_counts.retain(|&(_, interval_start), _| interval_start >= cutoff);
     }
 
 
-    pub fn get_interval_stats(&self) -> (u64, u32, u32) {
+    /// Start of the current interval, transactions accepted from `source` in it and its limit.
+    pub fn get_interval_stats(&self, source: SourceId) -> (u64, u32, u32) {
+        let current_block = self.block_simulator.current_block();
+        let interval_start = self.get_interval_start(current_block);
+        let current_count = self.source_interval_counts.get(&(source, interval_start)).unwrap_or(&0);
+        (interval_start, *current_count, self.source_limits(source).max_per_interval)
+    }
+
+    /// Like `get_interval_stats`, counting all sources against the global limit.
+    pub fn get_global_interval_stats(&self) -> (u64, u32, Option<u32>) {
         let current_block = self.block_simulator.current_block();
         let interval_start = self.get_interval_start(current_block);
         let current_count = self.interval_counts.get(&interval_start).unwrap_or(&0);
-        (interval_start, *current_count, self.max_per_interval)
+        (interval_start, *current_count, self.global_max_per_interval)
+    }
+
+    /// Current-interval counts of every source that submitted in it, with their limits.
+    pub fn get_source_interval_stats(&self) -> BTreeMap<SourceId, (u32, u32)> {
+        let interval_start = self.get_interval_start(self.block_simulator.current_block());
+        self.source_interval_counts
+            .iter()
+            .filter(|((_, start), _)| *start == interval_start)
+            .map(|(&(source, _), &count)| (source, (count, self.source_limits(source).max_per_interval)))
+            .collect()
     }
 
-    pub fn blocks_until_allowed(&self) -> u64 {
-        match self.next_allowed_block() {
+    pub fn blocks_until_allowed(&self, source: SourceId) -> u64 {
+        match self.next_allowed_block(source) {
             Some(next_allowed) => {
                 let current = self.block_simulator.current_block();
                 if next_allowed > current {
@@ -192,9 +402,9 @@ impl TransactionValidator {
         }
     }
 
-    fn next_allowed_block(&self) -> Option<u64> {
-        if let Some(last_block) = self.last_submission {
-            Some(last_block + self.min_block_interval)
+    fn next_allowed_block(&self, source: SourceId) -> Option<u64> {
+        if let Some(&last_block) = self.last_submissions.get(&source) {
+            Some(last_block + self.source_limits(source).min_block_interval)
         } else {
             None
         }
@@ -213,28 +423,50 @@ pub struct UnsignedPallet<T> {
     validator: TransactionValidator,
     data_store: HashMap<u64, T>,
     transaction_history: Vec<(u64, u64)>,
+    /// Validity of each stored nonce and the block its data expires at.
+    validity: HashMap<u64, (ValidTransaction, u64)>,
 }
 
-impl <T: std::fmt::Debug + Clone> UnsignedPallet<T> {
+impl <T: std::fmt::Debug + Clone + Encode> UnsignedPallet<T> {
     pub fn new(validator: TransactionValidator) -> Self {
         Self {
             validator,
             data_store: HashMap::new(),
             transaction_history: Vec::new(),
+            validity: HashMap::new(),
         }
     }
 
     pub fn submit_unsigned(&mut self, data: T, nonce: u64) -> Result<(), ValidationError> {
         let current_block = self.validator.block_simulator().current_block();
-        let transaction = UnsignedTransaction::new(data.clone(), current_block, nonce);
+        self.submit_unsigned_transaction(UnsignedTransaction::new(data, current_block, nonce))
+    }
 
-        self.validator.accept_transaction(&transaction)?;
+    /// Submits a transaction created at an earlier block, e.g. by `TransactionFactory`; the
+    /// older it is, the lower it ranks and the sooner it expires.
+    pub fn submit_unsigned_transaction(&mut self, transaction: UnsignedTransaction<T>) -> Result<(), ValidationError> {
+        let current_block = self.validator.block_simulator().current_block();
+        let validity = self.validator.accept_transaction(&transaction)?;
 
-        self.data_store.insert(nonce, data);
+        let nonce = transaction.nonce;
+        let expires_at = current_block.saturating_add(validity.longevity);
+        self.validity.insert(nonce, (validity, expires_at));
+        self.data_store.insert(nonce, transaction.data);
         self.transaction_history.push((current_block, nonce));
         Ok(())
     }
 
+    pub fn get_validity(&self, nonce: u64) -> Option<&ValidTransaction> {
+        self.validity.get(&nonce).map(|(validity, _)| validity)
+    }
+
+    /// Stored data by nonce, highest priority first and the lowest nonce among equals.
+    pub fn ranked_data(&self) -> Vec<(u64, &T)> {
+        let mut ranked = self.get_all_data();
+        ranked.sort_by_key(|(nonce, _)| (std::cmp::Reverse(self.validity[nonce].0.priority), *nonce));
+        ranked
+    }
+
     pub fn get_data(&self, nonce: u64) -> Option<&T> {
         self.data_store.get(&nonce)
     }
@@ -249,30 +481,50 @@ impl <T: std::fmt::Debug + Clone> UnsignedPallet<T> {
         &self.transaction_history
     }
 
-    /// Get the validator statistics
-    pub fn get_validator_stats(&self) -> (u64, u32, u32) {
-        self.validator.get_interval_stats()
+    /// Get the validator statistics of `source`
+    pub fn get_validator_stats(&self, source: SourceId) -> (u64, u32, u32) {
+        self.validator.get_interval_stats(source)
     }
 
-    pub fn next_submission_info(&self) -> (Option<u64>, u64) {
-        let next_block = self.validator.next_allowed_block();
-        let blocks_until = self.validator.blocks_until_allowed();
+    pub fn next_submission_info(&self, source: SourceId) -> (Option<u64>, u64) {
+        let next_block = self.validator.next_allowed_block(source);
+        let blocks_until = self.validator.blocks_until_allowed(source);
         (next_block, blocks_until)
     }
 
     pub fn advance_block(&mut self) {
         self.validator.block_simulator_mut().next_block();
         self.validator.cleanup_old_intervals();
+        self.expire_data();
     }
 
     pub fn advance_blocks(&mut self, count: u64) {
         self.validator.block_simulator_mut().advance_blocks(count);
         self.validator.cleanup_old_intervals();
+        self.expire_data();
+    }
+
+    /// Drops the data whose longevity ran out; its history entry stays.
+    fn expire_data(&mut self) {
+        let current_block = self.current_block();
+        let data_store = &mut self.data_store;
+        self.validity.retain(|nonce, (_, expires_at)| {
+            let live = *expires_at > current_block;
+            if !live {
+                data_store.remove(nonce);
+            }
+            live
+        });
     }
 
     pub fn current_block(&self) -> u64 {
         self.validator.block_simulator().current_block()
     }
+
+    /// See `TransactionValidator::rotate_authorities`.
+    pub fn rotate_authorities(&mut self, at_block: u64, authorities: impl IntoIterator<Item = PublicKey>) {
+        self.validator.rotate_authorities(at_block, authorities);
+    }
 }
 
 pub struct TransactionFactory {
@@ -333,7 +585,7 @@ mod tests {
         let [tx_1, tx_2, tx_3] = create_transactions();
 
         let result = validator.validate_transaction(&tx_1);
-        assert_eq!(result, ValidationResult::Valid);
+        assert!(matches!(result, ValidationResult::Valid(_)));
         let _ = validator.accept_transaction(&tx_1);
 
         validator.block_simulator_mut().next_block();
@@ -404,9 +656,9 @@ mod tests {
         let [tx_1, _, _] = create_transactions();
 
         let _ = validator.accept_transaction(&tx_1);
-        assert_eq!(validator.get_interval_stats(), (0, 1, 2));
+        assert_eq!(validator.get_interval_stats(DEFAULT_SOURCE), (0, 1, 2));
         validator.block_simulator.current_block = 15;
-        assert_eq!(validator.get_interval_stats(), (10, 0, 2));
+        assert_eq!(validator.get_interval_stats(DEFAULT_SOURCE), (10, 0, 2));
     }
 
     #[test]
@@ -414,8 +666,8 @@ mod tests {
         let mut validator = create_validator();
         let [tx_1, _, _] = create_transactions();
         let _ = validator.accept_transaction(&tx_1);
-        assert!(validator.next_allowed_block().is_some());
-        assert_eq!(validator.next_allowed_block().unwrap_or_default(), 3);
+        assert!(validator.next_allowed_block(DEFAULT_SOURCE).is_some());
+        assert_eq!(validator.next_allowed_block(DEFAULT_SOURCE).unwrap_or_default(), 3);
     }
 
     #[test]
@@ -423,7 +675,7 @@ mod tests {
         let mut validator = create_validator();
         let [tx_1, _, _] = create_transactions();
         let _ = validator.accept_transaction(&tx_1);
-        assert_eq!(validator.blocks_until_allowed(), 2);
+        assert_eq!(validator.blocks_until_allowed(DEFAULT_SOURCE), 2);
     }
 
     #[test]
@@ -460,6 +712,161 @@ mod tests {
         assert_eq!(all_data, vec![(1, &"tx_1"), (2, &"tx_2")]);
         
     }
+
+    #[test]
+    fn sources_are_rate_limited_separately() {
+        const ALICE: SourceId = 1;
+        const BOB: SourceId = 2;
+        let mut validator = create_validator().with_source_limits(BOB, SourceLimits::new(3, 0));
+
+        assert!(validator.accept_transaction(&UnsignedTransaction::new("a1", 1, 1).with_source(ALICE)).is_ok());
+        // alice has to wait, but that does not hold bob back
+        let result = validator.accept_transaction(&UnsignedTransaction::new("a2", 1, 2).with_source(ALICE));
+        assert_eq!(result, Err(ValidationError::TooEarly));
+        for nonce in 3..6 {
+            assert!(validator.accept_transaction(&UnsignedTransaction::new("b", 1, nonce).with_source(BOB)).is_ok());
+        }
+        let result = validator.accept_transaction(&UnsignedTransaction::new("b", 1, 6).with_source(BOB));
+        assert_eq!(result, Err(ValidationError::TooManyTransactions));
+
+        assert_eq!(validator.blocks_until_allowed(ALICE), 2);
+        assert_eq!(validator.blocks_until_allowed(BOB), 0);
+        assert_eq!(validator.get_interval_stats(ALICE), (0, 1, MAX));
+        assert_eq!(validator.get_interval_stats(BOB), (0, 3, 3));
+        assert_eq!(validator.get_interval_stats(DEFAULT_SOURCE), (0, 0, MAX));
+        assert_eq!(validator.get_global_interval_stats(), (0, 4, None));
+        assert_eq!(validator.get_source_interval_stats(), BTreeMap::from([(ALICE, (1, MAX)), (BOB, (3, 3))]));
+    }
+
+    #[test]
+    fn global_limit_caps_all_sources_together() {
+        let mut validator = create_validator().with_global_limit(3);
+        for (nonce, source) in [(1, 1), (2, 2), (3, 3)] {
+            assert!(validator.accept_transaction(&UnsignedTransaction::new("tx", 1, nonce).with_source(source)).is_ok());
+        }
+        let result = validator.validate_transaction(&UnsignedTransaction::new("tx", 1, 4).with_source(4));
+        assert_eq!(result, ValidationResult::Invalid(ValidationError::GlobalLimitReached));
+        assert_eq!(validator.get_global_interval_stats(), (0, 3, Some(3)));
+
+        // the next interval starts from zero
+        validator.block_simulator_mut().advance_blocks(INTERVAL);
+        validator.cleanup_old_intervals();
+        assert!(validator.accept_transaction(&UnsignedTransaction::new("tx", 11, 4).with_source(4)).is_ok());
+        assert_eq!(validator.get_source_interval_stats(), BTreeMap::from([(4, (1, MAX))]));
+    }
+
+    #[test]
+    fn signed_payloads_must_come_from_an_authority() {
+        let alice = Keypair::from_seed(b"alice");
+        let mallory = Keypair::from_seed(b"mallory");
+        let mut validator = create_validator().with_authorities([alice.public()]);
+
+        let unsigned = UnsignedTransaction::new("price", 1, 1);
+        let result = validator.validate_transaction(&unsigned);
+        assert_eq!(result, ValidationResult::Invalid(ValidationError::BadSignature));
+
+        let result = validator.validate_transaction(&unsigned.clone().signed(&mallory));
+        assert_eq!(result, ValidationResult::Invalid(ValidationError::UnknownAuthority));
+
+        let mut tampered = unsigned.clone().signed(&alice);
+        tampered.data = "forged price";
+        let result = validator.validate_transaction(&tampered);
+        assert_eq!(result, ValidationResult::Invalid(ValidationError::BadSignature));
+
+        assert!(validator.accept_transaction(&unsigned.signed(&alice)).is_ok());
+    }
+
+    #[test]
+    fn signed_payloads_must_use_the_signers_source() {
+        let alice = Keypair::from_seed(b"alice");
+        let bob = Keypair::from_seed(b"bob");
+        let mut validator = create_validator().with_authorities([alice.public(), bob.public()]);
+
+        // bob is authority 1, so he cannot submit as alice's source 0 or a made-up source
+        for source in [0, 2] {
+            let transaction = UnsignedTransaction::new("price", 1, 1).with_source(source).signed(&bob);
+            assert_eq!(validator.accept_transaction(&transaction), Err(ValidationError::SourceMismatch));
+        }
+        let transaction = UnsignedTransaction::new("price", 1, 1).with_source(1).signed(&bob);
+        assert!(validator.accept_transaction(&transaction).is_ok());
+        assert_eq!(validator.get_interval_stats(0), (0, 0, MAX));
+        assert_eq!(validator.get_interval_stats(1), (0, 1, MAX));
+    }
+
+    #[test]
+    fn authority_sets_rotate_at_the_given_block() {
+        let alice = Keypair::from_seed(b"alice");
+        let bob = Keypair::from_seed(b"bob");
+        let validator = TransactionValidator::new(10, INTERVAL, 0, BlockSimulator::new(6));
+        let mut pallet: UnsignedPallet<&str> = UnsignedPallet::new(validator);
+
+        // anyone may submit until the first authority set takes effect
+        pallet.rotate_authorities(2, [alice.public()]);
+        pallet.rotate_authorities(4, [bob.public()]);
+        assert!(pallet.submit_unsigned("open", 1).is_ok());
+
+        pallet.advance_block();
+        assert_eq!(pallet.submit_unsigned("open", 2), Err(ValidationError::BadSignature));
+        let from_alice = UnsignedTransaction::new("alice", 2, 3).signed(&alice);
+        assert!(pallet.submit_unsigned_transaction(from_alice).is_ok());
+
+        pallet.advance_blocks(2);
+        let from_alice = UnsignedTransaction::new("alice", 4, 4).signed(&alice);
+        assert_eq!(pallet.submit_unsigned_transaction(from_alice), Err(ValidationError::UnknownAuthority));
+        let from_bob = UnsignedTransaction::new("bob", 4, 5).signed(&bob);
+        assert!(pallet.submit_unsigned_transaction(from_bob).is_ok());
+        assert_eq!(pallet.get_transaction_history(), &[(1, 1), (2, 3), (4, 5)]);
+    }
+
+    #[test]
+    fn valid_transactions_carry_pool_metadata() {
+        let mut validator = create_validator().with_priority(100).with_longevity(5).with_propagate(false);
+        validator.block_simulator_mut().advance_blocks(2);
+
+        let result = validator.validate_transaction(&UnsignedTransaction::new("price", 1, 7));
+        let expected = ValidTransaction {
+            priority: 98,
+            longevity: 3,
+            propagate: false,
+            provides: vec![unsigned_tag(7)],
+            requires: vec![],
+        };
+        assert_eq!(result, ValidationResult::Valid(expected.clone()));
+        assert_eq!(validator.accept_transaction(&UnsignedTransaction::new("price", 1, 7)), Ok(expected));
+
+        validator.block_simulator_mut().advance_blocks(3);
+        let result = validator.validate_transaction(&UnsignedTransaction::new("price", 1, 8));
+        assert_eq!(result, ValidationResult::Invalid(ValidationError::Stale));
+    }
+
+    #[test]
+    fn pallet_ranks_and_expires_unsigned_data() {
+        let validator = TransactionValidator::new(10, INTERVAL, 0, BlockSimulator::new(6)).with_longevity(4);
+        let mut pallet: UnsignedPallet<&str> = UnsignedPallet::new(validator);
+        let mut factory = TransactionFactory::new();
+        let old = factory.create_transaction("old", 1);
+        pallet.advance_blocks(2);
+        let fresh = factory.create_transaction("fresh", 3);
+
+        pallet.submit_unsigned_transaction(old).unwrap();
+        pallet.submit_unsigned_transaction(fresh).unwrap();
+        assert_eq!(pallet.get_validity(1).unwrap().priority, DEFAULT_UNSIGNED_PRIORITY - 2);
+        assert_eq!(pallet.get_validity(2).unwrap().longevity, 4);
+        assert_eq!(pallet.ranked_data(), vec![(2, &"fresh"), (1, &"old")]);
+
+        // "old" was created at block 1, so it expires at block 5; "fresh" at block 7
+        pallet.advance_block();
+        assert_eq!(pallet.ranked_data(), vec![(2, &"fresh"), (1, &"old")]);
+        pallet.advance_block();
+        assert_eq!(pallet.ranked_data(), vec![(2, &"fresh")]);
+        assert!(pallet.get_validity(1).is_none());
+        pallet.advance_blocks(2);
+        assert!(pallet.get_all_data().is_empty());
+        assert_eq!(pallet.get_transaction_history(), &[(3, 1), (3, 2)]);
+
+        let stale = factory.create_transaction("stale", 3);
+        assert_eq!(pallet.submit_unsigned_transaction(stale), Err(ValidationError::Stale));
+    }
     
 
 }
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::common::serde_helpers::hex_bytes_map;
 
-#[derive(Debug, Clone)]
+/// JSON: `{"data":{"timestamp":"0x…"}}`, identifiers in key order, values as hex.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InherentData {
+    #[serde(with = "hex_bytes_map")]
     data: HashMap<String, Vec<u8>>,
 }
 
@@ -38,7 +42,8 @@ pub trait InherentDataProvider {
 }
 
 // Timestamp data structure
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+/// JSON: `{"millis":1700000000000}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
 pub struct Timestamp {
     /// Milliseconds since Unix epoch
     pub millis: u64,
@@ -207,7 +212,8 @@ impl BlockConstructor {
 
 }
 
-#[derive(Debug, Clone)]
+/// JSON: `{"block_number":1,"inherent_data":{"data":{…}},"timestamp":1700000000000}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub block_number: u64,
     pub inherent_data: InherentData,
@@ -304,11 +310,35 @@ mod tests {
         assert_eq!(validation_result.err(), Some("Required timestamp not found."));
     }
 
-}
-
-
-
-
-
-
+    #[test]
+    fn block_json_round_trip() {
+        let provider = TimestampProvider::new().with_custom_timestamp(Timestamp::from_millis(1_700_000_000_000));
+        let mut constructor = BlockConstructor::new(1);
+        constructor.register_provider(Box::new(provider));
+        let block = constructor.build_block().unwrap();
+
+        let json = serde_json::to_string(&block).unwrap();
+        assert_eq!(
+            json,
+            r#"{"block_number":1,"inherent_data":{"data":{"timestamp":"0x0068e5cf8b010000"}},"timestamp":1700000000000}"#
+        );
+
+        let decoded: Block = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.block_number, block.block_number);
+        assert_eq!(decoded.timestamp, block.timestamp);
+        assert_eq!(decoded.get_inherent_timestamp(), Ok(Some(Timestamp::from_millis(1_700_000_000_000))));
+    }
 
+    #[test]
+    fn inherent_data_json_is_key_ordered() {
+        let mut inherent_data = InherentData::new();
+        inherent_data.put_data("zeta", vec![2]);
+        inherent_data.put_data("alpha", vec![1]);
+        let json = serde_json::to_string(&inherent_data).unwrap();
+        assert_eq!(json, r#"{"data":{"alpha":"0x01","zeta":"0x02"}}"#);
+
+        let decoded: InherentData = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.get_data("alpha"), Some(&vec![1]));
+        assert_eq!(decoded.get_data("zeta"), Some(&vec![2]));
+    }
+}
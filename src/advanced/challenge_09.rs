@@ -1,5 +1,7 @@
 
 
+use serde::{Deserialize, Serialize};
+
 pub trait Config {
     type AccountId: Clone + PartialEq + core::fmt::Debug;
     type BlockNumber: Copy + PartialOrd + core::ops::Add<Output = Self::BlockNumber> ;
@@ -16,7 +18,12 @@ pub struct Task<AccountId, BlockNumber> {
 }
 
 
-#[derive(Clone, Debug, PartialEq)]
+/// JSON: `{"TaskCreated":{"task_id":1,"creator":1}}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T::AccountId: Serialize",
+    deserialize = "T::AccountId: Deserialize<'de>"
+))]
 pub enum Event<T: Config> {
     TaskCreated { task_id: u32, creator: T::AccountId },
     TaskExpired { task_id: u32 },
@@ -24,7 +31,7 @@ pub enum Event<T: Config> {
 }
 
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Error {
     BadOrigin,
     MaxTasksReached,
@@ -69,7 +76,7 @@ impl<T: Config> Pallet<T> {
         Ok(())
      }
     
-    pub fn on_initialize(&mut self, block_number: T::BlockNumber) -> u64 {
+    pub fn on_initialize(&mut self, _block_number: T::BlockNumber) -> u64 {
         10_000
     }
 
@@ -142,7 +149,6 @@ impl<T: Config> Pallet<T> {
 }
 
 #[cfg(test)]
-
 mod tests {
     use crate::advanced::challenge_09::{Config, Get};
     use super::*;
@@ -203,7 +209,7 @@ mod tests {
     fn create_task_max_tasks_fail() {
         let mut pallet = Pallet::<TestConfig>::new();
 
-        for i in 1..51 {
+        for _ in 1..51 {
             let result = pallet.create_task(Origin::Signed(1), 1);
             assert!(result.is_ok());
         }
@@ -227,4 +233,20 @@ mod tests {
             Event::TaskCreated { task_id: 1, creator: creator_account }
         ]);
     }
+
+    #[test]
+    fn events_json_round_trip() {
+        let mut pallet = Pallet::<TestConfig>::new();
+        assert!(pallet.create_task(Origin::Signed(7), 1).is_ok());
+        pallet.on_finalize(6);
+        pallet.on_runtime_upgrade();
+        let events = pallet.take_events();
+
+        let json = serde_json::to_string(&events).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"TaskCreated":{"task_id":1,"creator":7}},{"TaskExpired":{"task_id":1}},{"RuntimeUpgraded":{"old_version":1,"new_version":2}}]"#
+        );
+        assert_eq!(serde_json::from_str::<Vec<Event<TestConfig>>>(&json).unwrap(), events);
+    }
 }
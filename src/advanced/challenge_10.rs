@@ -1,28 +1,178 @@
+use serde::{Deserialize, Serialize};
+use crate::advanced::challenge_02::{Weight, WeightMeter};
+use crate::advanced::challenge_06::UnsignedTransaction;
+use crate::common::codec::Encode;
+use crate::common::hashing::blake2_256;
+use crate::common::serde_helpers::{hex_bytes, hex_bytes_list, hex_hash};
+use crate::common::signature::{Keypair, PublicKey, Signature};
+
 pub type TransactionHash = [u8; 32];
 
-pub fn simple_hash(data: &[u8]) -> TransactionHash {
-    let mut hash = [0u8; 32];
-    for (i, byte) in data.iter().enumerate() {
-        if i >= 32 { break; }
-        hash[i] = *byte;
-    }
-    hash
+/// Opaque marker a transaction provides to, or requires from, other transactions.
+pub type Tag = Vec<u8>;
+
+/// `sender` of transactions that have no signer, such as those from `challenge_06`.
+pub const UNSIGNED_SENDER: &str = "";
+
+/// Blocks a transaction may wait in the pool unless it asks for a different longevity.
+pub const DEFAULT_LONGEVITY: u64 = 64;
+
+/// Blocks a dropped or invalid transaction's hash stays banned from resubmission.
+pub const BAN_DURATION: u64 = 30;
+
+/// Weight charged for a transaction unless it declares its own.
+pub const BASE_TRANSACTION_WEIGHT: Weight = Weight { ref_time: 100_000, proof_size: 0 };
+
+/// Tag provided by `sender`'s transaction with `nonce`.
+pub fn nonce_tag(sender: &str, nonce: u64) -> Tag {
+    let mut tag = sender.encode();
+    nonce.encode_to(&mut tag);
+    tag
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// JSON: `{"hash":"0x…","sender":"alice","nonce":1,"priority":100,"data":"0x010203","signature":null}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Transaction {
+    #[serde(with = "hex_hash")]
     pub hash: TransactionHash,
     pub sender: String,
     pub nonce: u64,
     pub priority: u64,  
+    #[serde(with = "hex_bytes")]
     pub data: Vec<u8>,
+    /// The sender's signature over the encoded payload; see `Transaction::signed`.
+    #[serde(default)]
+    pub signature: Option<Signature>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Error {
     InvalidNonce,
     TransactionPoolFull,
-    TransactionDuplicate
+    TransactionDuplicate,
+    /// `hash` is not the hash of the transaction's contents.
+    InvalidHash,
+    /// The pool already holds a transaction with this sender and nonce at equal or higher priority.
+    TooLowPriority,
+    /// The sender already has `max_per_sender` transactions in the pool.
+    SenderLimitReached,
+    /// The pool's validator rejected the transaction.
+    Invalid(InvalidTransaction),
+    /// The pool's validator could not tell whether the transaction is valid.
+    Unknown(UnknownTransaction),
+    /// The hash was recently dropped or rejected as invalid and cannot be resubmitted yet.
+    TemporarilyBanned,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DropReason {
+    /// The transaction outlived its `longevity` without being included.
+    Expired,
+    /// A block enacted by `TransactionPool::maintain` used the transaction's sender and nonce.
+    Stale,
+    /// `TransactionPool::revalidate` found the transaction invalid.
+    Invalid,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InvalidTransaction {
+    /// The signature is missing or does not match the payload and the sender's key.
+    BadProof,
+    /// A validator-specific reason.
+    Custom(u8),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UnknownTransaction {
+    /// The validator does not know the sender's key.
+    CannotLookup,
+    /// The validator has no rules for unsigned transactions.
+    NoUnsignedValidator,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransactionValidityError {
+    Invalid(InvalidTransaction),
+    Unknown(UnknownTransaction),
+}
+
+/// How a valid transaction takes part in the pool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidTransaction {
+    pub priority: u64,
+    pub longevity: u64,
+    pub provides: Vec<Tag>,
+    pub requires: Vec<Tag>,
+}
+
+impl ValidTransaction {
+    /// The transaction's own priority and `DEFAULT_LONGEVITY`. Signed transactions provide
+    /// their own `(sender, nonce)` tag and require the previous nonce's; unsigned ones
+    /// provide their hash and require nothing.
+    pub fn default_for(transaction: &Transaction) -> Self {
+        let (provides, requires) = if transaction.is_unsigned() {
+            (vec![transaction.hash.to_vec()], Vec::new())
+        } else {
+            let requires = match transaction.nonce {
+                0 | 1 => Vec::new(),
+                nonce => vec![nonce_tag(&transaction.sender, nonce - 1)],
+            };
+            (vec![nonce_tag(&transaction.sender, transaction.nonce)], requires)
+        };
+        Self { priority: transaction.priority, longevity: DEFAULT_LONGEVITY, provides, requires }
+    }
+}
+
+pub type TransactionValidity = Result<ValidTransaction, TransactionValidityError>;
+
+/// Decides whether a transaction may enter the pool, and with which priority, longevity and tags.
+/// The pool calls it from `TransactionPool::submit_transaction` and `TransactionPool::revalidate`.
+pub trait TransactionValidator {
+    fn validate_transaction(&self, transaction: &Transaction) -> TransactionValidity;
+}
+
+/// Accepts signed transactions whose signature verifies against the sender's registered key,
+/// with `ValidTransaction::default_for`'s validity.
+#[derive(Default)]
+pub struct SignatureValidator {
+    keys: HashMap<String, PublicKey>,
+}
+
+impl SignatureValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_account(mut self, sender: &str, key: PublicKey) -> Self {
+        self.keys.insert(sender.to_string(), key);
+        self
+    }
+}
+
+impl TransactionValidator for SignatureValidator {
+    fn validate_transaction(&self, transaction: &Transaction) -> TransactionValidity {
+        if transaction.is_unsigned() {
+            return Err(TransactionValidityError::Unknown(UnknownTransaction::NoUnsignedValidator));
+        }
+        let key = self.keys
+            .get(&transaction.sender)
+            .ok_or(TransactionValidityError::Unknown(UnknownTransaction::CannotLookup))?;
+        match &transaction.signature {
+            Some(signature) if key.verify(&transaction.encode(), signature) => Ok(ValidTransaction::default_for(transaction)),
+            _ => Err(TransactionValidityError::Invalid(InvalidTransaction::BadProof)),
+        }
+    }
+}
+
+/// Something that happened to a pooled transaction, drained with `TransactionPool::take_events`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PoolEvent {
+    /// `old` was removed in favour of `new`, a higher-priority transaction with the same sender and nonce.
+    Replaced { old: TransactionHash, new: TransactionHash },
+    /// `hash` was dropped to make room for `by`, which pays a higher priority.
+    Evicted { hash: TransactionHash, by: TransactionHash },
+    /// `hash` was removed by `TransactionPool::on_new_block` or `TransactionPool::maintain`.
+    Dropped { hash: TransactionHash, reason: DropReason },
 }
 
 
@@ -34,50 +184,271 @@ impl Transaction {
         priority: u64,
         data: Vec<u8>,
     ) -> Self {
-        let hash_input = format!("{}:{}:{}", sender, nonce, priority);
-        let hash = simple_hash(hash_input.as_bytes());
-
-        Self {
-            hash,
+        let mut transaction = Self {
+            hash: [0; 32],
             sender,
             nonce,
             priority,
             data,
-        }
+            signature: None,
+        };
+        transaction.hash = transaction.compute_hash();
+        transaction
+    }
+
+    /// Signs the encoded payload with `keypair`. The hash does not cover the signature.
+    pub fn signed(mut self, keypair: &Keypair) -> Self {
+        self.signature = Some(keypair.sign(&self.encode()));
+        self
+    }
+
+    /// Wraps an unsigned transaction from `challenge_06`; its nonce only tells it apart from others.
+    pub fn from_unsigned<T: Encode>(transaction: &UnsignedTransaction<T>, priority: u64) -> Self {
+        Self::new(UNSIGNED_SENDER.to_string(), transaction.nonce, priority, transaction.data.encode())
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        self.sender == UNSIGNED_SENDER
+    }
+
+    /// BLAKE2b-256 of the SCALE-style encoding of every field except `hash` and `signature`.
+    pub fn compute_hash(&self) -> TransactionHash {
+        blake2_256(&self.encode())
+    }
+
+    /// Bytes the transaction takes up in a block.
+    pub fn encoded_len(&self) -> usize {
+        self.encode().len()
+    }
+}
+
+/// Bytes: `sender` as a string, `nonce` and `priority` as `u64`, then `data` as a byte vector.
+impl Encode for Transaction {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.sender.encode_to(out);
+        self.nonce.encode_to(out);
+        self.priority.encode_to(out);
+        self.data.encode_to(out);
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// JSON: `"Pending"` or `"Ready"`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PoolStatus {
+    /// Future queue: some required tag is provided neither on chain nor by a ready transaction.
     Pending,
+    /// Ready queue: every required tag is provided on chain or by another ready transaction.
     Ready
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// JSON: `{"transaction":{…},"status":"Ready","priority":100,"provides":["0x…"],"requires":[],"inserted_at":0,"longevity":64,"weight":{…}}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PoolTransaction {
     pub transaction: Transaction,
     pub status: PoolStatus,
+    /// Priority from validation, which orders the pool; `transaction.priority` by default.
+    pub priority: u64,
+    #[serde(with = "hex_bytes_list")]
+    pub provides: Vec<Tag>,
+    #[serde(with = "hex_bytes_list")]
+    pub requires: Vec<Tag>,
+    /// Block number at which the pool accepted the transaction.
+    pub inserted_at: u64,
+    /// Blocks after `inserted_at` at which the transaction expires.
+    pub longevity: u64,
+    /// Execution cost counted against `BlockLimits::max_weight`.
+    pub weight: Weight,
 }
 
 impl PoolTransaction {
+    /// Uses `ValidTransaction::default_for`.
     pub fn new(transaction: Transaction) -> Self {
+        let validity = ValidTransaction::default_for(&transaction);
+        Self::from_validity(transaction, validity)
+    }
+
+    pub fn with_tags(transaction: Transaction, provides: Vec<Tag>, requires: Vec<Tag>) -> Self {
+        let validity = ValidTransaction { provides, requires, ..ValidTransaction::default_for(&transaction) };
+        Self::from_validity(transaction, validity)
+    }
+
+    pub fn from_validity(transaction: Transaction, validity: ValidTransaction) -> Self {
         Self {
             transaction,
-            status: PoolStatus::Pending, 
+            status: PoolStatus::Pending,
+            priority: validity.priority,
+            provides: validity.provides,
+            requires: validity.requires,
+            inserted_at: 0,
+            longevity: validity.longevity,
+            weight: BASE_TRANSACTION_WEIGHT,
         }
     }
 
+    pub fn with_weight(mut self, weight: Weight) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_longevity(mut self, longevity: u64) -> Self {
+        self.longevity = longevity;
+        self
+    }
+
+    /// Whether the transaction has outlived its longevity by `block_number`.
+    pub fn is_expired(&self, block_number: u64) -> bool {
+        block_number >= self.inserted_at.saturating_add(self.longevity)
+    }
+
     pub fn can_be_included(&self) -> bool {
         matches!(self.status, PoolStatus::Ready)
     }
 }
 
-use std::collections::HashMap;
+/// Bounds for `TransactionPool::build_block_with_limits`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockLimits {
+    pub max_weight: Weight,
+    /// Total `Transaction::encoded_len` of the block's transactions.
+    pub max_length: usize,
+    pub max_transactions: usize,
+}
+
+impl BlockLimits {
+    pub fn new(max_weight: Weight, max_length: usize) -> Self {
+        Self { max_weight, max_length, max_transactions: usize::MAX }
+    }
+
+    /// Only counts transactions; weight and length are unbounded.
+    pub fn unbounded() -> Self {
+        Self::new(Weight::from_parts(u64::MAX, u64::MAX), usize::MAX)
+    }
+
+    pub fn with_max_transactions(mut self, max_transactions: usize) -> Self {
+        self.max_transactions = max_transactions;
+        self
+    }
+}
+
+/// What `TransactionPool::build_block_with_limits` put in the block and what it passed over.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockSummary {
+    pub transactions: Vec<Transaction>,
+    pub consumed_weight: Weight,
+    pub consumed_length: usize,
+    /// Ready transactions left out because their weight or length did not fit.
+    pub skipped: Vec<TransactionHash>,
+}
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Snapshot returned by `TransactionPool::stats`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolStats {
+    pub ready: usize,
+    pub future: usize,
+    /// Hashes currently barred from resubmission.
+    pub banned: usize,
+    /// Pooled signed transactions per sender.
+    pub sender_depths: BTreeMap<String, usize>,
+    /// Blocks since the longest-waiting transaction entered the pool, if any is pooled.
+    pub oldest_age: Option<u64>,
+    /// Pooled transactions per priority bucket, keyed by the bucket's lowest priority:
+    /// `0`, `1`, `2..=3`, `4..=7` and so on.
+    pub priority_histogram: BTreeMap<u64, usize>,
+    /// Failed submissions per error since the pool was created.
+    pub rejections: HashMap<Error, usize>,
+    /// Transactions evicted to make room since the pool was created.
+    pub evictions: usize,
+}
+
+fn priority_bucket(priority: u64) -> u64 {
+    match priority {
+        0 => 0,
+        priority => 1 << (u64::BITS - 1 - priority.leading_zeros()),
+    }
+}
+
+/// One line of `TransactionPool::dump`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolEntry {
+    pub hash: TransactionHash,
+    pub sender: String,
+    pub nonce: u64,
+    pub priority: u64,
+    pub status: PoolStatus,
+    pub inserted_at: u64,
+    /// Required tags that neither the chain nor a ready transaction provides.
+    pub missing: Vec<Tag>,
+}
+
+/// Orders `TransactionPool::best`: highest priority first, then the lowest `(sender, nonce)`.
+type BestKey = (Reverse<u64>, String, u64, TransactionHash);
+/// Orders the eviction indexes: lowest priority first, then the highest nonce.
+type EvictionKey = (u64, Reverse<u64>, TransactionHash);
+
+impl PoolTransaction {
+    fn best_key(&self) -> BestKey {
+        let tx = &self.transaction;
+        (Reverse(self.priority), tx.sender.clone(), tx.nonce, tx.hash)
+    }
+
+    fn eviction_key(&self) -> EvictionKey {
+        let tx = &self.transaction;
+        (self.priority, Reverse(tx.nonce), tx.hash)
+    }
+
+    fn expires_at(&self) -> u64 {
+        self.inserted_at.saturating_add(self.longevity)
+    }
+}
+
+/// Removes `hash` from the set indexed under `tag`, dropping the set once it is empty.
+fn unindex(index: &mut HashMap<Tag, HashSet<TransactionHash>>, tag: &Tag, hash: &TransactionHash) {
+    if let Some(hashes) = index.get_mut(tag) {
+        hashes.remove(hash);
+        if hashes.is_empty() {
+            index.remove(tag);
+        }
+    }
+}
 
+/// Transactions are indexed so that submitting, evicting, expiring and taking the next best
+/// transaction cost a logarithmic number of steps plus the dependants whose status changes,
+/// rather than a pass over the whole pool.
 pub struct TransactionPool {
     transactions: HashMap<TransactionHash, PoolTransaction>,
-    sender_nonces: HashMap<String, u64>,
+    /// Hashes of each sender's pooled signed transactions, ordered by nonce.
+    by_sender: HashMap<String, BTreeMap<u64, TransactionHash>>,
+    /// Nonces of each sender's transactions in the current chain.
+    chain_nonces: HashMap<String, BTreeSet<u64>>,
+    /// Transactions in the current chain with the tags they were included with, so a
+    /// retracted block can give its tags back.
+    on_chain: HashMap<TransactionHash, PoolTransaction>,
+    /// How many transactions in the current chain provide each tag.
+    provided_on_chain: HashMap<Tag, usize>,
+    /// Ready transactions providing each tag.
+    ready_providers: HashMap<Tag, HashSet<TransactionHash>>,
+    /// Pooled transactions, ready or not, requiring each tag.
+    dependants: HashMap<Tag, HashSet<TransactionHash>>,
+    /// Ready transactions whose required tags are all on chain, best first. For signed
+    /// transactions with default tags this is each sender's lowest pooled nonce.
+    best: BTreeSet<BestKey>,
+    ready_by_priority: BTreeSet<EvictionKey>,
+    future_by_priority: BTreeSet<EvictionKey>,
+    /// Pooled transactions by the block at which they expire.
+    by_expiry: BTreeSet<(u64, TransactionHash)>,
     max_pool_size: usize,
+    max_per_sender: Option<usize>,
+    events: Vec<PoolEvent>,
+    block_number: u64,
+    /// Hashes that may not be resubmitted, with the block at which their ban ends.
+    banned: HashMap<TransactionHash, u64>,
+    validator: Option<Box<dyn TransactionValidator + Send>>,
+    /// Failed submissions per error, for `stats`.
+    rejections: HashMap<Error, usize>,
+    evictions: usize,
 }
 
 
@@ -85,206 +456,986 @@ impl TransactionPool {
     pub fn new(max_pool_size: usize) -> Self {
         Self {
             transactions: HashMap::new(),
-            sender_nonces: HashMap::new(),
-            max_pool_size
+            by_sender: HashMap::new(),
+            chain_nonces: HashMap::new(),
+            on_chain: HashMap::new(),
+            provided_on_chain: HashMap::new(),
+            ready_providers: HashMap::new(),
+            dependants: HashMap::new(),
+            best: BTreeSet::new(),
+            ready_by_priority: BTreeSet::new(),
+            future_by_priority: BTreeSet::new(),
+            by_expiry: BTreeSet::new(),
+            max_pool_size,
+            max_per_sender: None,
+            events: Vec::new(),
+            block_number: 0,
+            banned: HashMap::new(),
+            validator: None,
+            rejections: HashMap::new(),
+            evictions: 0,
         }
     }
-    
-    fn update_ready_status(&mut self) {
-        for (_, pool_tx) in self.transactions.iter_mut() {
-            let next_nonce = self.sender_nonces.get(&pool_tx.transaction.sender).copied().unwrap_or(0) + 1;
-            if pool_tx.transaction.nonce == next_nonce {
-                pool_tx.status = PoolStatus::Ready;
-            }
-        }
+
+    /// Validates every `submit_transaction` with `validator`.
+    pub fn with_validator(mut self, validator: impl TransactionValidator + Send + 'static) -> Self {
+        self.validator = Some(Box::new(validator));
+        self
     }
-    
-    pub fn submit_transaction(&mut self, transaction: Transaction) -> Result<(), Error> {
-        if self.transactions.len() >= self.max_pool_size {return Err(Error::TransactionPoolFull)}
-        if self.transactions.contains_key(&transaction.hash) {return Err(Error::TransactionDuplicate)}
-        if transaction.nonce <= 0 {return Err(Error::InvalidNonce)}
-        
-        let hash = transaction.hash;
-        let pool_tx = PoolTransaction::new(transaction);
-        self.transactions.insert(hash, pool_tx);
-        self.update_ready_status();
-        Ok(())
+
+    /// Caps how many signed transactions one sender may have in the pool.
+    pub fn with_max_per_sender(mut self, max_per_sender: usize) -> Self {
+        self.max_per_sender = Some(max_per_sender);
+        self
     }
 
-    pub fn build_block(&mut self, max_transactions: usize) -> Vec<Transaction> {
-        let mut ready_hashes: Vec<(TransactionHash, u64)> = self.transactions
+    /// Whether `tag` is provided on chain or by a ready transaction.
+    fn is_available(&self, tag: &Tag) -> bool {
+        self.provided_on_chain.contains_key(tag) || self.ready_providers.contains_key(tag)
+    }
+
+    /// Moves `hash` from the future queue to the ready one, returning the tags it is the
+    /// first to make available.
+    fn mark_ready(&mut self, hash: &TransactionHash) -> Vec<Tag> {
+        let pool_tx = &self.transactions[hash];
+        let newly_available: Vec<Tag> = pool_tx.provides
             .iter()
-            .filter(|(_, pool_tx)| pool_tx.status == PoolStatus::Ready)
-            .map(|(hash, pool_tx)| (*hash, pool_tx.transaction.priority))
+            .filter(|tag| !self.is_available(tag))
+            .cloned()
             .collect();
-        
-        ready_hashes.sort_by_key(|(_, priority)| std::cmp::Reverse(*priority));
-        
-        let mut selected_transactions = Vec::new();
-        for (hash, _) in ready_hashes.iter().take(max_transactions) {
-            if let Some(pool_tx) = self.transactions.get(hash) {
-                selected_transactions.push(pool_tx.transaction.clone());
-            }
+        for tag in &pool_tx.provides {
+            self.ready_providers.entry(tag.clone()).or_default().insert(*hash);
+        }
+        self.future_by_priority.remove(&pool_tx.eviction_key());
+        self.ready_by_priority.insert(pool_tx.eviction_key());
+        if pool_tx.requires.iter().all(|tag| self.provided_on_chain.contains_key(tag)) {
+            self.best.insert(pool_tx.best_key());
         }
-        for transaction in &selected_transactions {
-            // Remover do pool
-            self.transactions.remove(&transaction.hash);
-            
-            // Atualizar o nonce do sender
-            let current_nonce = self.sender_nonces.get(&transaction.sender).copied().unwrap_or(0);
-            self.sender_nonces.insert(transaction.sender.clone(), current_nonce.max(transaction.nonce));
+        if let Some(pool_tx) = self.transactions.get_mut(hash) {
+            pool_tx.status = PoolStatus::Ready;
         }
-        self.update_ready_status();
+        newly_available
+    }
 
-        selected_transactions
+    fn mark_pending(&mut self, hash: &TransactionHash) {
+        let pool_tx = &self.transactions[hash];
+        for tag in &pool_tx.provides {
+            unindex(&mut self.ready_providers, tag, hash);
+        }
+        self.ready_by_priority.remove(&pool_tx.eviction_key());
+        self.future_by_priority.insert(pool_tx.eviction_key());
+        self.best.remove(&pool_tx.best_key());
+        if let Some(pool_tx) = self.transactions.get_mut(hash) {
+            pool_tx.status = PoolStatus::Pending;
+        }
     }
- 
-    
-    pub fn get_transaction(&self, hash: &TransactionHash) -> Option<&PoolTransaction> {
-        self.transactions.get(hash)
+
+    /// Promotes every pending transaction in `candidates` whose required tags are all
+    /// available, then rechecks the dependants of each tag that becomes available.
+    fn promote(&mut self, mut candidates: Vec<TransactionHash>) {
+        while let Some(hash) = candidates.pop() {
+            let Some(pool_tx) = self.transactions.get(&hash) else { continue };
+            if pool_tx.can_be_included() || !pool_tx.requires.iter().all(|tag| self.is_available(tag)) {
+                continue;
+            }
+            for tag in self.mark_ready(&hash) {
+                candidates.extend(self.dependants.get(&tag).into_iter().flatten());
+            }
+        }
     }
-    
-    pub fn get_ready_count(&self) -> usize {
-        self.transactions
-            .values()
-            .filter(|pool_tx| pool_tx.can_be_included())
-            .count()
+
+    /// Demotes every ready transaction that requires, directly or through other ready
+    /// transactions, one of the `withdrawn` tags, then promotes back those that the chain
+    /// and the rest of the ready queue still support. Demoting the whole set first keeps
+    /// transactions that provide each other's tags from holding each other up.
+    fn demote(&mut self, mut withdrawn: Vec<Tag>) {
+        let mut affected = Vec::new();
+        let mut seen = HashSet::new();
+        while let Some(tag) = withdrawn.pop() {
+            for hash in self.dependants.get(&tag).into_iter().flatten() {
+                let pool_tx = &self.transactions[hash];
+                if pool_tx.can_be_included() && seen.insert(*hash) {
+                    affected.push(*hash);
+                    withdrawn.extend(pool_tx.provides.iter().cloned());
+                }
+            }
+        }
+        for hash in &affected {
+            self.mark_pending(hash);
+        }
+        self.promote(affected);
     }
-    
-    pub fn get_total_count(&self) -> usize {
-        self.transactions.len()
+
+    /// Adds `pool_tx` to the future queue and promotes it, with anything it unlocks, if its
+    /// required tags are available.
+    fn insert_transaction(&mut self, mut pool_tx: PoolTransaction) {
+        pool_tx.status = PoolStatus::Pending;
+        let transaction = &pool_tx.transaction;
+        let hash = transaction.hash;
+        if !transaction.is_unsigned() {
+            self.by_sender.entry(transaction.sender.clone()).or_default().insert(transaction.nonce, hash);
+        }
+        for tag in &pool_tx.requires {
+            self.dependants.entry(tag.clone()).or_default().insert(hash);
+        }
+        self.future_by_priority.insert(pool_tx.eviction_key());
+        self.by_expiry.insert((pool_tx.expires_at(), hash));
+        self.transactions.insert(hash, pool_tx);
+        self.promote(vec![hash]);
     }
-    
-    pub fn get_sender_next_expected_nonce(&self, sender: &str) -> u64 {
-        self.sender_nonces.get(sender).copied().unwrap_or(0) + 1
+
+    /// Submits with the validity the pool's validator gives, or `ValidTransaction::default_for`'s
+    /// without one. Transactions the validator finds invalid are banned for `BAN_DURATION` blocks,
+    /// except for a bad proof: the hash leaves out the signature, so banning it would let a
+    /// badly signed copy block the genuine transaction.
+    pub fn submit_transaction(&mut self, transaction: Transaction) -> Result<(), Error> {
+        let result = self.validate_and_admit(transaction);
+        self.count_rejection(&result);
+        result
     }
-    
-    
-}
 
-#[cfg(test)]
+    fn validate_and_admit(&mut self, transaction: Transaction) -> Result<(), Error> {
+        let Some(validator) = &self.validator else {
+            return self.admit(PoolTransaction::new(transaction));
+        };
+        // a forged hash must not get someone else's transaction banned
+        self.pre_check(&transaction)?;
+        match validator.validate_transaction(&transaction) {
+            Ok(validity) => self.admit(PoolTransaction::from_validity(transaction, validity)),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::BadProof)) => Err(Error::Invalid(InvalidTransaction::BadProof)),
+            Err(TransactionValidityError::Invalid(reason)) => {
+                self.ban(transaction.hash);
+                Err(Error::Invalid(reason))
+            }
+            Err(TransactionValidityError::Unknown(reason)) => Err(Error::Unknown(reason)),
+        }
+    }
 
-mod tests {
-    use crate::advanced::challenge_10::{Error, PoolStatus, Transaction, TransactionPool};
+    /// Checks every pooled transaction again, e.g. after the validator's keys changed. Invalid
+    /// ones are dropped with `DropReason::Invalid` and banned; unknown ones stay.
+    pub fn revalidate(&mut self) {
+        let Some(validator) = &self.validator else { return };
+        let mut invalid: Vec<TransactionHash> = self.transactions
+            .values()
+            .filter(|pool_tx| {
+                matches!(validator.validate_transaction(&pool_tx.transaction), Err(TransactionValidityError::Invalid(_)))
+            })
+            .map(|pool_tx| pool_tx.transaction.hash)
+            .collect();
+        invalid.sort();
+        for hash in invalid {
+            self.remove_transaction(&hash);
+            self.ban(hash);
+            self.events.push(PoolEvent::Dropped { hash, reason: DropReason::Invalid });
+        }
+    }
 
-    #[test]
-    fn submit_transaction_test() {
-        let mut pool = TransactionPool::new(10);
-        let tx1 = Transaction::new("alice".to_string(), 1, 100, vec![1,2,3]);
-        let tx2 = Transaction::new("alice".to_string(), 2, 100, vec![1,2,3]);
-        let result = pool.submit_transaction(tx1.clone());
-        assert!(result.is_ok());
-        let result = pool.submit_transaction(tx2.clone());
-        assert!(result.is_ok());
-        assert_eq!(pool.get_ready_count(), 1);
+    /// Submits with tags set by the caller's validation instead of the nonce-derived defaults,
+    /// without consulting the pool's validator.
+    pub fn submit_transaction_with_tags(
+        &mut self,
+        transaction: Transaction,
+        provides: Vec<Tag>,
+        requires: Vec<Tag>,
+    ) -> Result<(), Error> {
+        self.submit_pool_transaction(PoolTransaction::with_tags(transaction, provides, requires))
     }
-    
-    #[test]
-    fn submit_transaction_invalid_nonce_fail() {
-        let mut pool = TransactionPool::new(10);
-        let tx1 = Transaction::new("alice".to_string(), 0, 100, vec![1,2,3]);
-        let result = pool.submit_transaction(tx1.clone());
-        assert!(result.is_err());
-        assert_eq!(result, Err(Error::InvalidNonce));
+
+    /// Submits a transaction the caller has already validated, without consulting the pool's
+    /// validator; `inserted_at` is overwritten with the current block.
+    ///
+    /// A transaction with the same sender and nonce as a pooled one replaces it only when its
+    /// priority is strictly higher, emitting `PoolEvent::Replaced`. When the pool is full, the
+    /// newcomer evicts the lowest-priority future transaction it outbids, or failing that the
+    /// lowest-priority ready one, emitting `PoolEvent::Evicted`. Evicted hashes and those
+    /// rejected for a stale nonce are banned for `BAN_DURATION` blocks.
+    pub fn submit_pool_transaction(&mut self, pool_tx: PoolTransaction) -> Result<(), Error> {
+        let result = self.admit(pool_tx);
+        self.count_rejection(&result);
+        result
     }
-    
-    #[test]
-    fn submit_transaction_transaction_duplicate_fail() {
-        let mut pool = TransactionPool::new(10);
-        let tx1 = Transaction::new("alice".to_string(), 1, 100, vec![1,2,3]);
-        let result = pool.submit_transaction(tx1.clone());
-        assert!(result.is_ok());
-        let result = pool.submit_transaction(tx1.clone());
-        assert!(result.is_err());
-        assert_eq!(result, Err(Error::TransactionDuplicate));
+
+    fn count_rejection(&mut self, result: &Result<(), Error>) {
+        if let Err(error) = result {
+            *self.rejections.entry(error.clone()).or_default() += 1;
+        }
     }
-    
-    #[test]
-    fn submit_transaction_transaction_pool_full_fail() {
-        let mut pool = TransactionPool::new(1);
-        let tx1 = Transaction::new("alice".to_string(), 1, 100, vec![1,2,3]);
-        let result = pool.submit_transaction(tx1.clone());
-        assert!(result.is_ok());
-        let tx2 = Transaction::new("alice".to_string(), 2, 100, vec![1,2,3]);
-        let result = pool.submit_transaction(tx2.clone());
-        assert_eq!(result, Err(Error::TransactionPoolFull));
+
+    /// Checks that need no validation: the hash matches the contents and is neither banned nor pooled.
+    fn pre_check(&self, transaction: &Transaction) -> Result<(), Error> {
+        if transaction.hash != transaction.compute_hash() {return Err(Error::InvalidHash)}
+        if self.is_banned(&transaction.hash) {return Err(Error::TemporarilyBanned)}
+        if self.transactions.contains_key(&transaction.hash) {return Err(Error::TransactionDuplicate)}
+        Ok(())
     }
-    
-    #[test]
-    fn nonce_dependence_test() {
-        let mut pool = TransactionPool::new(10);
-        let tx1 = Transaction::new("alice".to_string(), 1, 100, vec![1,2,3]);
-        let tx2 = Transaction::new("alice".to_string(), 2, 100, vec![1,2,3]);
 
-        let tx1_bob = Transaction::new("bob".to_string(), 1, 100, vec![1,2,3]);
-        let tx2_bob = Transaction::new("bob".to_string(), 2, 100, vec![1,2,3]);
+    /// `submit_pool_transaction` without counting a rejection, for the pool's own resubmissions.
+    fn admit(&mut self, mut pool_tx: PoolTransaction) -> Result<(), Error> {
+        pool_tx.inserted_at = self.block_number;
+        self.pre_check(&pool_tx.transaction)?;
+        let transaction = &pool_tx.transaction;
 
-        let _ = pool.submit_transaction(tx1.clone());
-        let _ = pool.submit_transaction(tx2.clone());
-        let _ = pool.submit_transaction(tx1_bob.clone());
-        let _ = pool.submit_transaction(tx2_bob.clone());
-
-        {
-            let tx1_pool_result = pool.get_transaction(&tx1.hash).unwrap();
-            let tx2_pool_result = pool.get_transaction(&tx2.hash).unwrap();
-            let tx1_bob_pool_result = pool.get_transaction(&tx1_bob.hash).unwrap();
-            let tx2_bob_pool_result = pool.get_transaction(&tx2_bob.hash).unwrap();
-            
-            assert_eq!(tx1_pool_result.status, PoolStatus::Ready);
-            assert_eq!(tx2_pool_result.status, PoolStatus::Pending);
-            assert_eq!(tx1_bob_pool_result.status, PoolStatus::Ready);
-            assert_eq!(tx2_bob_pool_result.status, PoolStatus::Pending);
-        }
-
-        // Build a block that can include both ready transactions
-        let block = pool.build_block(2);
-        assert_eq!(block.len(), 2);
-
-        let tx2_pool_after_opt = pool.get_transaction(&tx2.hash);
-        let tx2_bob_pool_after_opt = pool.get_transaction(&tx2_bob.hash);
-
-        assert!(tx2_pool_after_opt.is_some());
-        assert!(tx2_bob_pool_after_opt.is_some());
-
-        let tx2_pool_after = tx2_pool_after_opt.unwrap();
-        let tx2_bob_pool_after = tx2_bob_pool_after_opt.unwrap();
-        
-        // Now that both tx1 and tx1_bob are included, tx2 and tx2_bob should be ready
-        assert_eq!(tx2_pool_after.status, PoolStatus::Ready);
-        assert_eq!(tx2_bob_pool_after.status, PoolStatus::Ready);
-    }
-     #[test]
-        fn test_build_block_selects_by_priority_and_updates_state() {
-            // --- ARRANGE ---
-            // Setup a pool with enough capacity.
-            let mut pool = TransactionPool::new(10);
+        let hash = transaction.hash;
+        let mut replaced = None;
+        if !transaction.is_unsigned() {
+            if transaction.nonce < self.get_sender_next_expected_nonce(&transaction.sender) {
+                self.ban(hash);
+                return Err(Error::InvalidNonce);
+            }
+            if let Some(old_hash) = self.by_sender.get(&transaction.sender).and_then(|queue| queue.get(&transaction.nonce)) {
+                if pool_tx.priority <= self.transactions[old_hash].priority {return Err(Error::TooLowPriority)}
+                replaced = Some(*old_hash);
+            }
+            let sender_count = self.by_sender.get(&transaction.sender).map_or(0, BTreeMap::len);
+            if replaced.is_none() && self.max_per_sender.is_some_and(|max| sender_count >= max) {
+                return Err(Error::SenderLimitReached);
+            }
+        }
+        let mut evicted = None;
+        if replaced.is_none() && self.transactions.len() >= self.max_pool_size {
+            evicted = Some(self.eviction_candidate(&pool_tx).ok_or(Error::TransactionPoolFull)?);
+        }
 
-            // Create transactions with varying priorities. All have nonce 1 to be "Ready".
-            let tx_high_priority = Transaction::new("alice".to_string(), 1, 200, vec![1]);
-            let tx_medium_priority = Transaction::new("bob".to_string(), 1, 150, vec![2]);
-            let tx_low_priority = Transaction::new("charlie".to_string(), 1, 100, vec![3]);
+        if let Some(old) = replaced {
+            self.remove_transaction(&old);
+            self.events.push(PoolEvent::Replaced { old, new: hash });
+        }
+        if let Some(victim) = evicted {
+            self.remove_transaction(&victim);
+            self.ban(victim);
+            self.evictions += 1;
+            self.events.push(PoolEvent::Evicted { hash: victim, by: hash });
+        }
+        self.insert_transaction(pool_tx);
+        Ok(())
+    }
 
-            // Create a pending transaction with a high priority. 
-            // This should NOT be included in the block because its status is not "Ready".
-            let tx_pending = Transaction::new("alice".to_string(), 2, 999, vec![4]);
+    /// Advances the pool to `block_number`: lifts bans that have run out, then drops every
+    /// transaction that has outlived its longevity, soonest to expire first, banning its hash
+    /// and emitting `PoolEvent::Dropped`.
+    pub fn on_new_block(&mut self, block_number: u64) {
+        self.block_number = block_number;
+        self.banned.retain(|_, until| *until > block_number);
 
-            // Submit all transactions to the pool.
-            pool.submit_transaction(tx_high_priority.clone()).unwrap();
-            pool.submit_transaction(tx_medium_priority.clone()).unwrap();
-            pool.submit_transaction(tx_low_priority.clone()).unwrap();
-            pool.submit_transaction(tx_pending.clone()).unwrap();
+        while let Some(&(expires_at, hash)) = self.by_expiry.first() {
+            if expires_at > block_number {break}
+            self.remove_transaction(&hash);
+            self.ban(hash);
+            self.events.push(PoolEvent::Dropped { hash, reason: DropReason::Expired });
+        }
+    }
 
-            // Initial sanity check: 3 transactions should be ready for inclusion.
-            assert_eq!(pool.get_ready_count(), 3, "There should be 3 ready transactions before building the block");
+    fn ban(&mut self, hash: TransactionHash) {
+        self.banned.insert(hash, self.block_number.saturating_add(BAN_DURATION));
+    }
 
-            // --- ACT ---
-            // Build a block with a limit of 2 transactions.
-            let block = pool.build_block(2);
+    pub fn is_banned(&self, hash: &TransactionHash) -> bool {
+        self.banned.contains_key(hash)
+    }
 
-            // --- ASSERT ---
-            // 1. Test: Transaction limit per block.
-            // The block should contain exactly 2 transactions.
-            assert_eq!(block.len(), 2, "Block should be limited to 2 transactions");
+    /// Lowest-priority transaction below `newcomer`'s, looking at the future queue before the
+    /// ready one. Transactions `newcomer` depends on, its sender's lower nonces and providers of
+    /// tags it requires, are never picked, or it would evict its way into the future queue for good.
+    fn eviction_candidate(&self, newcomer: &PoolTransaction) -> Option<TransactionHash> {
+        let transaction = &newcomer.transaction;
+        let is_dependency = |candidate: &PoolTransaction| {
+            let earlier_nonce = !transaction.is_unsigned()
+                && candidate.transaction.sender == transaction.sender
+                && candidate.transaction.nonce < transaction.nonce;
+            earlier_nonce || candidate.provides.iter().any(|tag| newcomer.requires.contains(tag))
+        };
+        [&self.future_by_priority, &self.ready_by_priority]
+            .into_iter()
+            .find_map(|queue| {
+                queue
+                    .iter()
+                    .take_while(|(lowest, _, _)| *lowest < newcomer.priority)
+                    .find(|(_, _, hash)| !is_dependency(&self.transactions[hash]))
+            })
+            .map(|(_, _, hash)| *hash)
+    }
+
+    /// Removes `hash` from the pool and every index, leaving its dependants' status untouched.
+    fn detach(&mut self, hash: &TransactionHash) -> Option<PoolTransaction> {
+        let pool_tx = self.transactions.remove(hash)?;
+        let transaction = &pool_tx.transaction;
+        if let Some(queue) = self.by_sender.get_mut(&transaction.sender) {
+            if queue.get(&transaction.nonce) == Some(hash) {
+                queue.remove(&transaction.nonce);
+            }
+            if queue.is_empty() {
+                self.by_sender.remove(&transaction.sender);
+            }
+        }
+        for tag in &pool_tx.requires {
+            unindex(&mut self.dependants, tag, hash);
+        }
+        if pool_tx.can_be_included() {
+            for tag in &pool_tx.provides {
+                unindex(&mut self.ready_providers, tag, hash);
+            }
+            self.ready_by_priority.remove(&pool_tx.eviction_key());
+            self.best.remove(&pool_tx.best_key());
+        } else {
+            self.future_by_priority.remove(&pool_tx.eviction_key());
+        }
+        self.by_expiry.remove(&(pool_tx.expires_at(), *hash));
+        Some(pool_tx)
+    }
+
+    /// Removes `hash`, moving whatever only it made ready back to the future queue.
+    fn remove_transaction(&mut self, hash: &TransactionHash) -> Option<PoolTransaction> {
+        let pool_tx = self.detach(hash)?;
+        if pool_tx.can_be_included() {
+            self.demote(pool_tx.provides.clone());
+        }
+        Some(pool_tx)
+    }
+
+    /// Moves `hash` from the pool into a block.
+    fn include(&mut self, hash: &TransactionHash) -> Option<Transaction> {
+        let pool_tx = self.detach(hash)?;
+        let transaction = pool_tx.transaction.clone();
+        self.record_on_chain(pool_tx);
+        Some(transaction)
+    }
+
+    /// Adds a transaction to the current chain. Tags it is the first to put on chain promote
+    /// pending dependants and let ready ones that required nothing else from the pool join `best`.
+    fn record_on_chain(&mut self, pool_tx: PoolTransaction) {
+        let transaction = &pool_tx.transaction;
+        if !transaction.is_unsigned() {
+            self.chain_nonces.entry(transaction.sender.clone()).or_default().insert(transaction.nonce);
+        }
+        let mut newly_provided = Vec::new();
+        for tag in &pool_tx.provides {
+            let count = self.provided_on_chain.entry(tag.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                newly_provided.push(tag);
+            }
+        }
+        let dependants: Vec<TransactionHash> = newly_provided
+            .into_iter()
+            .flat_map(|tag| self.dependants.get(tag).into_iter().flatten())
+            .copied()
+            .collect();
+        self.on_chain.insert(transaction.hash, pool_tx);
+
+        let unlocked: Vec<BestKey> = dependants
+            .iter()
+            .map(|dependant| &self.transactions[dependant])
+            .filter(|dependant| {
+                dependant.can_be_included()
+                    && dependant.requires.iter().all(|tag| self.provided_on_chain.contains_key(tag))
+            })
+            .map(PoolTransaction::best_key)
+            .collect();
+        self.best.extend(unlocked);
+        self.promote(dependants);
+    }
+
+    /// Takes `transaction` out of the current chain, withdrawing the tags only it provided
+    /// there, and returns it with the tags it was included with.
+    fn retract(&mut self, transaction: &Transaction) -> PoolTransaction {
+        let Some(pool_tx) = self.on_chain.remove(&transaction.hash) else {
+            return PoolTransaction::new(transaction.clone());
+        };
+        if let Some(nonces) = self.chain_nonces.get_mut(&transaction.sender) {
+            nonces.remove(&transaction.nonce);
+            if nonces.is_empty() {
+                self.chain_nonces.remove(&transaction.sender);
+            }
+        }
+        let mut withdrawn = Vec::new();
+        for tag in &pool_tx.provides {
+            if let Some(count) = self.provided_on_chain.get_mut(tag) {
+                *count -= 1;
+                if *count == 0 {
+                    self.provided_on_chain.remove(tag);
+                    withdrawn.push(tag.clone());
+                }
+            }
+        }
+        self.demote(withdrawn);
+        pool_tx
+    }
+
+    /// Brings the pool in line with a new best chain: `retracted_blocks` left it and
+    /// `enacted_blocks` joined it, each holding transactions as returned by `build_block`.
+    ///
+    /// Retracted transactions give back their tags and nonces. Enacted ones are pruned from the
+    /// pool, and pooled transactions whose nonce the new chain has used are dropped with
+    /// `DropReason::Stale`. Retracted transactions that no enacted block includes are then
+    /// resubmitted; any that no longer pass submission are discarded.
+    pub fn maintain(&mut self, enacted_blocks: &[Vec<Transaction>], retracted_blocks: &[Vec<Transaction>]) {
+        let mut retracted: Vec<Option<PoolTransaction>> = retracted_blocks
+            .iter()
+            .flatten()
+            .map(|transaction| Some(self.retract(transaction)))
+            .collect();
+        let retracted_index: HashMap<TransactionHash, usize> = retracted
+            .iter()
+            .enumerate()
+            .filter_map(|(index, pool_tx)| Some((pool_tx.as_ref()?.transaction.hash, index)))
+            .collect();
+
+        for transaction in enacted_blocks.iter().flatten() {
+            if self.on_chain.contains_key(&transaction.hash) {continue}
+            // A transaction moving between forks keeps the tags and priority it was validated with.
+            let pool_tx = self.detach(&transaction.hash)
+                .or_else(|| retracted_index.get(&transaction.hash).and_then(|index| retracted[*index].take()))
+                .unwrap_or_else(|| PoolTransaction::new(transaction.clone()));
+            self.record_on_chain(pool_tx);
+        }
+
+        let mut stale: Vec<(&String, u64, TransactionHash)> = self.by_sender
+            .iter()
+            .flat_map(|(sender, queue)| {
+                queue
+                    .range(..self.get_sender_next_expected_nonce(sender))
+                    .map(move |(nonce, hash)| (sender, *nonce, *hash))
+            })
+            .collect();
+        stale.sort();
+        let stale: Vec<TransactionHash> = stale.into_iter().map(|(_, _, hash)| hash).collect();
+        for hash in stale {
+            self.remove_transaction(&hash);
+            self.events.push(PoolEvent::Dropped { hash, reason: DropReason::Stale });
+        }
+
+        for pool_tx in retracted.into_iter().flatten() {
+            if !self.on_chain.contains_key(&pool_tx.transaction.hash) {
+                let _ = self.admit(pool_tx);
+            }
+        }
+    }
+
+    /// Takes up to `max_transactions` ready transactions, highest priority first.
+    pub fn build_block(&mut self, max_transactions: usize) -> Vec<Transaction> {
+        self.build_block_with_limits(&BlockLimits::unbounded().with_max_transactions(max_transactions)).transactions
+    }
+
+    /// Fills a block with ready transactions, highest priority first, within `limits`.
+    ///
+    /// A transaction only competes once every tag it requires is provided on chain or by a
+    /// transaction already in the block, so dependencies such as a sender's earlier nonces
+    /// always come first. Equal priorities go to the lowest `(sender, nonce)`, keeping
+    /// blocks deterministic. A transaction whose weight or length does not fit in what is
+    /// left is skipped and stays in the pool; whatever depends on it, such as the sender's
+    /// later nonces, is held back with it while smaller transactions still get in.
+    pub fn build_block_with_limits(&mut self, limits: &BlockLimits) -> BlockSummary {
+        let mut transactions = Vec::new();
+        let mut skipped = Vec::new();
+        let mut set_aside = Vec::new();
+        let mut meter = WeightMeter::new(limits.max_weight);
+        let mut consumed_length = 0usize;
+        while transactions.len() < limits.max_transactions {
+            let Some(key) = self.best.pop_first() else { break };
+            let hash = key.3;
+            let pool_tx = &self.transactions[&hash];
+            let length = consumed_length.saturating_add(pool_tx.transaction.encoded_len());
+            if length > limits.max_length || meter.consume(pool_tx.weight).is_err() {
+                skipped.push(hash);
+                set_aside.push(key);
+                continue;
+            }
+            consumed_length = length;
+            transactions.extend(self.include(&hash));
+        }
+        self.best.extend(set_aside);
+
+        BlockSummary {
+            transactions,
+            consumed_weight: meter.consumed(),
+            consumed_length,
+            skipped,
+        }
+    }
+
+    /// Counts and distributions over the whole pool; costs a pass over every transaction.
+    pub fn stats(&self) -> PoolStats {
+        let mut sender_depths = BTreeMap::new();
+        for (sender, queue) in &self.by_sender {
+            sender_depths.insert(sender.clone(), queue.len());
+        }
+        let mut priority_histogram = BTreeMap::new();
+        for pool_tx in self.transactions.values() {
+            *priority_histogram.entry(priority_bucket(pool_tx.priority)).or_default() += 1;
+        }
+        let oldest_age = self.transactions
+            .values()
+            .map(|pool_tx| self.block_number.saturating_sub(pool_tx.inserted_at))
+            .max();
+        PoolStats {
+            ready: self.get_ready_count(),
+            future: self.get_future_count(),
+            banned: self.banned.len(),
+            sender_depths,
+            oldest_age,
+            priority_histogram,
+            rejections: self.rejections.clone(),
+            evictions: self.evictions,
+        }
+    }
+
+    /// Every pooled transaction: the ready ones in the order `build_block` would include them
+    /// given unlimited room, then the future ones by sender and nonce with the tags they miss.
+    pub fn dump(&self) -> Vec<PoolEntry> {
+        let entry = |pool_tx: &PoolTransaction, missing: Vec<Tag>| PoolEntry {
+            hash: pool_tx.transaction.hash,
+            sender: pool_tx.transaction.sender.clone(),
+            nonce: pool_tx.transaction.nonce,
+            priority: pool_tx.priority,
+            status: pool_tx.status.clone(),
+            inserted_at: pool_tx.inserted_at,
+            missing,
+        };
+
+        let mut entries = Vec::new();
+        let mut best = self.best.clone();
+        let mut included: HashSet<TransactionHash> = HashSet::new();
+        let mut provided: HashSet<&Tag> = HashSet::new();
+        while let Some(key) = best.pop_first() {
+            let pool_tx = &self.transactions[&key.3];
+            included.insert(key.3);
+            entries.push(entry(pool_tx, Vec::new()));
+            provided.extend(&pool_tx.provides);
+            for tag in &pool_tx.provides {
+                for dependant in self.dependants.get(tag).into_iter().flatten() {
+                    let dependant = &self.transactions[dependant];
+                    let unlocked = dependant.requires
+                        .iter()
+                        .all(|tag| self.provided_on_chain.contains_key(tag) || provided.contains(tag));
+                    if dependant.can_be_included() && unlocked && !included.contains(&dependant.transaction.hash) {
+                        best.insert(dependant.best_key());
+                    }
+                }
+            }
+        }
+
+        let mut future: Vec<&PoolTransaction> = self.transactions.values().filter(|pool_tx| !pool_tx.can_be_included()).collect();
+        future.sort_by(|a, b| {
+            let (a, b) = (&a.transaction, &b.transaction);
+            (&a.sender, a.nonce, a.hash).cmp(&(&b.sender, b.nonce, b.hash))
+        });
+        for pool_tx in future {
+            let missing = pool_tx.requires.iter().filter(|tag| !self.is_available(tag)).cloned().collect();
+            entries.push(entry(pool_tx, missing));
+        }
+        entries
+    }
+
+    pub fn take_events(&mut self) -> Vec<PoolEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn get_transaction(&self, hash: &TransactionHash) -> Option<&PoolTransaction> {
+        self.transactions.get(hash)
+    }
+
+    pub fn get_ready_count(&self) -> usize {
+        self.ready_by_priority.len()
+    }
+
+    pub fn get_future_count(&self) -> usize {
+        self.future_by_priority.len()
+    }
+
+    pub fn get_total_count(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn get_sender_next_expected_nonce(&self, sender: &str) -> u64 {
+        self.chain_nonces.get(sender).and_then(|nonces| nonces.last()).map_or(1, |nonce| nonce + 1)
+    }
+
+
+}
+
+use std::sync::Arc;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Mutex;
+
+/// What happened to a transaction watched through `WatchedPool::submit_and_watch`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransactionStatus {
+    /// In the future queue, waiting for a required tag.
+    Future,
+    /// In the ready queue.
+    Ready,
+    /// Included in the block with this number.
+    InBlock(u64),
+    /// The block with this number that included it left the chain.
+    Retracted(u64),
+    /// The block with this number that included it was finalized.
+    Finalized(u64),
+    /// Evicted from a full pool or expired.
+    Dropped,
+    /// The chain made it unusable, e.g. by using its nonce.
+    Invalid,
+    /// Replaced in the pool by the transaction with this hash.
+    Usurped(TransactionHash),
+}
+
+impl TransactionStatus {
+    /// Whether no further status can follow; the stream ends after a final status.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Finalized(_) | Self::Dropped | Self::Invalid | Self::Usurped(_))
+    }
+}
+
+/// `Stream` of a watched transaction's statuses, ending after a final one.
+pub type StatusStream = UnboundedReceiver<TransactionStatus>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub number: u64,
+    pub transactions: Vec<Transaction>,
+}
+
+struct Watcher {
+    sender: UnboundedSender<TransactionStatus>,
+    /// Queue last reported while the transaction is pooled; `None` while it is in a block.
+    queue: Option<PoolStatus>,
+}
+
+struct WatchedPoolState {
+    pool: TransactionPool,
+    watchers: HashMap<TransactionHash, Watcher>,
+    /// Watched transactions in each unfinalized block of the current chain.
+    blocks: BTreeMap<u64, Vec<TransactionHash>>,
+}
+
+impl WatchedPoolState {
+    /// Sends `status` to `hash`'s watcher, forgetting the watcher once the status is final
+    /// or nobody is listening.
+    fn notify(&mut self, hash: &TransactionHash, status: TransactionStatus) {
+        let Some(watcher) = self.watchers.get(hash) else { return };
+        let is_final = status.is_final();
+        if watcher.sender.unbounded_send(status).is_err() || is_final {
+            self.watchers.remove(hash);
+        }
+    }
+
+    /// Reports the pool's events, then every pooled watched transaction whose queue changed.
+    fn sync(&mut self) {
+        for event in self.pool.take_events() {
+            match event {
+                PoolEvent::Replaced { old, new } => self.notify(&old, TransactionStatus::Usurped(new)),
+                PoolEvent::Evicted { hash, .. } | PoolEvent::Dropped { hash, reason: DropReason::Expired } => {
+                    self.notify(&hash, TransactionStatus::Dropped)
+                }
+                PoolEvent::Dropped { hash, reason: DropReason::Stale | DropReason::Invalid } => {
+                    self.notify(&hash, TransactionStatus::Invalid)
+                }
+            }
+        }
+
+        let changed: Vec<(TransactionHash, PoolStatus)> = self.watchers
+            .iter()
+            .filter_map(|(hash, watcher)| {
+                let status = &self.pool.get_transaction(hash)?.status;
+                (watcher.queue.as_ref() != Some(status)).then(|| (*hash, status.clone()))
+            })
+            .collect();
+        for (hash, status) in changed {
+            let update = match status {
+                PoolStatus::Pending => TransactionStatus::Future,
+                PoolStatus::Ready => TransactionStatus::Ready,
+            };
+            if let Some(watcher) = self.watchers.get_mut(&hash) {
+                watcher.queue = Some(status);
+            }
+            self.notify(&hash, update);
+        }
+    }
+
+    fn in_block(&mut self, number: u64, hash: &TransactionHash) {
+        let Some(watcher) = self.watchers.get_mut(hash) else { return };
+        watcher.queue = None;
+        self.blocks.entry(number).or_default().push(*hash);
+        self.notify(hash, TransactionStatus::InBlock(number));
+    }
+}
+
+/// A `TransactionPool` shared between tasks that streams status updates for watched
+/// transactions. Statuses are driven by the calls made on it: submissions, `build_block`,
+/// `on_new_block`, `maintain` and `finalize`.
+#[derive(Clone)]
+pub struct WatchedPool {
+    state: Arc<Mutex<WatchedPoolState>>,
+}
+
+impl WatchedPool {
+    pub fn new(pool: TransactionPool) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(WatchedPoolState {
+                pool,
+                watchers: HashMap::new(),
+                blocks: BTreeMap::new(),
+            })),
+        }
+    }
+
+    pub async fn submit(&self, transaction: Transaction) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        let result = state.pool.submit_transaction(transaction);
+        state.sync();
+        result
+    }
+
+    /// Submits `transaction` and streams its statuses, starting with `Future` or `Ready`.
+    pub async fn submit_and_watch(&self, transaction: Transaction) -> Result<StatusStream, Error> {
+        let mut state = self.state.lock().await;
+        let hash = transaction.hash;
+        let result = state.pool.submit_transaction(transaction);
+        let (sender, receiver) = unbounded();
+        if result.is_ok() {
+            state.watchers.insert(hash, Watcher { sender, queue: None });
+        }
+        state.sync();
+        result.map(|()| receiver)
+    }
+
+    /// Builds block `number` from the pool, reporting `InBlock` for the watched transactions in it.
+    pub async fn build_block(&self, number: u64, limits: &BlockLimits) -> Block {
+        let mut state = self.state.lock().await;
+        let transactions = state.pool.build_block_with_limits(limits).transactions;
+        for transaction in &transactions {
+            state.in_block(number, &transaction.hash);
+        }
+        state.sync();
+        Block { number, transactions }
+    }
+
+    /// See `TransactionPool::revalidate`; invalid transactions are reported as `Invalid`.
+    pub async fn revalidate(&self) {
+        let mut state = self.state.lock().await;
+        state.pool.revalidate();
+        state.sync();
+    }
+
+    /// See `TransactionPool::on_new_block`; expired transactions are reported as `Dropped`.
+    pub async fn on_new_block(&self, block_number: u64) {
+        let mut state = self.state.lock().await;
+        state.pool.on_new_block(block_number);
+        state.sync();
+    }
+
+    /// See `TransactionPool::maintain`. Watched transactions in retracted blocks report
+    /// `Retracted`, followed by `InBlock` if an enacted block includes them, their queue if
+    /// they are back in the pool, or `Invalid` if they could not be resubmitted.
+    pub async fn maintain(&self, enacted: &[Block], retracted: &[Block]) {
+        let mut state = self.state.lock().await;
+        for block in retracted {
+            state.blocks.remove(&block.number);
+            for transaction in &block.transactions {
+                if let Some(watcher) = state.watchers.get_mut(&transaction.hash) {
+                    watcher.queue = None;
+                    state.notify(&transaction.hash, TransactionStatus::Retracted(block.number));
+                }
+            }
+        }
+
+        let enacted_blocks: Vec<Vec<Transaction>> = enacted.iter().map(|block| block.transactions.clone()).collect();
+        let retracted_blocks: Vec<Vec<Transaction>> = retracted.iter().map(|block| block.transactions.clone()).collect();
+        state.pool.maintain(&enacted_blocks, &retracted_blocks);
+        state.sync();
+
+        let mut included = HashSet::new();
+        for block in enacted {
+            for transaction in &block.transactions {
+                included.insert(transaction.hash);
+                state.in_block(block.number, &transaction.hash);
+            }
+        }
+        let lost: Vec<TransactionHash> = retracted
+            .iter()
+            .flat_map(|block| &block.transactions)
+            .map(|transaction| transaction.hash)
+            .filter(|hash| {
+                !included.contains(hash)
+                    && state.watchers.get(hash).is_some_and(|watcher| watcher.queue.is_none())
+            })
+            .collect();
+        for hash in lost {
+            state.notify(&hash, TransactionStatus::Invalid);
+        }
+    }
+
+    /// Reports `Finalized` for the watched transactions in blocks up to `number`, ending their streams.
+    pub async fn finalize(&self, number: u64) {
+        let mut state = self.state.lock().await;
+        let finalized = match number.checked_add(1) {
+            Some(next) => {
+                let pending = state.blocks.split_off(&next);
+                std::mem::replace(&mut state.blocks, pending)
+            }
+            None => std::mem::take(&mut state.blocks),
+        };
+        for (block_number, hashes) in finalized {
+            for hash in hashes {
+                state.notify(&hash, TransactionStatus::Finalized(block_number));
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+
+mod tests {
+    use crate::advanced::challenge_02::Weight;
+    use crate::advanced::challenge_06::UnsignedTransaction;
+    use crate::advanced::challenge_10::{
+        nonce_tag, Block, BlockLimits, DropReason, Error, PoolEvent, PoolStatus, PoolTransaction, Transaction,
+        TransactionPool, TransactionStatus, WatchedPool, BAN_DURATION, BASE_TRANSACTION_WEIGHT, DEFAULT_LONGEVITY,
+    };
+    use crate::advanced::challenge_10::{
+        InvalidTransaction, SignatureValidator, TransactionValidator, TransactionValidity, TransactionValidityError,
+        UnknownTransaction, ValidTransaction,
+    };
+    use crate::advanced::challenge_10::{Tag, TransactionHash};
+    use crate::common::codec::Encode;
+    use crate::common::serde_helpers::to_hex;
+    use crate::common::signature::Keypair;
+    use futures::StreamExt;
+    use std::cmp::Reverse;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    #[test]
+    fn submit_transaction_test() {
+        let mut pool = TransactionPool::new(10);
+        let tx1 = Transaction::new("alice".to_string(), 1, 100, vec![1,2,3]);
+        let tx2 = Transaction::new("alice".to_string(), 2, 100, vec![1,2,3]);
+        let result = pool.submit_transaction(tx1.clone());
+        assert!(result.is_ok());
+        let result = pool.submit_transaction(tx2.clone());
+        assert!(result.is_ok());
+        assert_eq!(pool.get_ready_count(), 2);
+    }
+    
+    #[test]
+    fn submit_transaction_invalid_nonce_fail() {
+        let mut pool = TransactionPool::new(10);
+        let tx1 = Transaction::new("alice".to_string(), 0, 100, vec![1,2,3]);
+        let result = pool.submit_transaction(tx1.clone());
+        assert!(result.is_err());
+        assert_eq!(result, Err(Error::InvalidNonce));
+    }
+    
+    #[test]
+    fn submit_transaction_transaction_duplicate_fail() {
+        let mut pool = TransactionPool::new(10);
+        let tx1 = Transaction::new("alice".to_string(), 1, 100, vec![1,2,3]);
+        let result = pool.submit_transaction(tx1.clone());
+        assert!(result.is_ok());
+        let result = pool.submit_transaction(tx1.clone());
+        assert!(result.is_err());
+        assert_eq!(result, Err(Error::TransactionDuplicate));
+    }
+    
+    #[test]
+    fn submit_transaction_transaction_pool_full_fail() {
+        let mut pool = TransactionPool::new(1);
+        let tx1 = Transaction::new("alice".to_string(), 1, 100, vec![1,2,3]);
+        let result = pool.submit_transaction(tx1.clone());
+        assert!(result.is_ok());
+        let tx2 = Transaction::new("alice".to_string(), 2, 100, vec![1,2,3]);
+        let result = pool.submit_transaction(tx2.clone());
+        assert_eq!(result, Err(Error::TransactionPoolFull));
+    }
+    
+    #[test]
+    fn nonce_dependence_test() {
+        let mut pool = TransactionPool::new(10);
+        let tx1 = Transaction::new("alice".to_string(), 1, 100, vec![1,2,3]);
+        let tx2 = Transaction::new("alice".to_string(), 2, 100, vec![1,2,3]);
+        let tx3 = Transaction::new("alice".to_string(), 3, 100, vec![1,2,3]);
+
+        let _ = pool.submit_transaction(tx1.clone());
+        let _ = pool.submit_transaction(tx3.clone());
+
+        // Nonce 2 is missing, so nonce 3 waits in the future queue
+        assert_eq!(pool.get_transaction(&tx1.hash).unwrap().status, PoolStatus::Ready);
+        assert_eq!(pool.get_transaction(&tx3.hash).unwrap().status, PoolStatus::Pending);
+        assert_eq!(pool.get_future_count(), 1);
+
+        // Filling the gap promotes nonce 3 without waiting for a block
+        let _ = pool.submit_transaction(tx2.clone());
+        assert_eq!(pool.get_transaction(&tx2.hash).unwrap().status, PoolStatus::Ready);
+        assert_eq!(pool.get_transaction(&tx3.hash).unwrap().status, PoolStatus::Ready);
+        assert_eq!(pool.get_future_count(), 0);
+
+        let block = pool.build_block(10);
+        let nonces: Vec<u64> = block.iter().map(|tx| tx.nonce).collect();
+        assert_eq!(nonces, vec![1, 2, 3]);
+        assert_eq!(pool.get_sender_next_expected_nonce("alice"), 4);
+        assert_eq!(pool.get_total_count(), 0);
+    }
+
+    #[test]
+    fn consecutive_nonces_are_included_in_order_across_senders() {
+        let mut pool = TransactionPool::new(10);
+        let alice1 = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let alice2 = Transaction::new("alice".to_string(), 2, 500, vec![]);
+        let bob1 = Transaction::new("bob".to_string(), 1, 100, vec![]);
+        let bob2 = Transaction::new("bob".to_string(), 2, 5, vec![]);
+        for tx in [&alice2, &bob2, &bob1, &alice1] {
+            pool.submit_transaction(tx.clone()).unwrap();
+        }
+
+        // alice's nonce 2 pays the most but only competes once her nonce 1 is in the block
+        let block = pool.build_block(3);
+        let hashes: Vec<_> = block.iter().map(|tx| tx.hash).collect();
+        assert_eq!(hashes, vec![bob1.hash, alice1.hash, alice2.hash]);
+        assert_eq!(pool.get_transaction(&bob2.hash).unwrap().status, PoolStatus::Ready);
+    }
+
+    #[test]
+    fn submit_transaction_stale_nonce_fail() {
+        let mut pool = TransactionPool::new(10);
+        pool.submit_transaction(Transaction::new("alice".to_string(), 1, 100, vec![])).unwrap();
+        pool.build_block(1);
+        let stale = Transaction::new("alice".to_string(), 1, 200, vec![]);
+        assert_eq!(pool.submit_transaction(stale), Err(Error::InvalidNonce));
+    }
+
+     #[test]
+        fn test_build_block_selects_by_priority_and_updates_state() {
+            // --- ARRANGE ---
+            // Setup a pool with enough capacity.
+            let mut pool = TransactionPool::new(10);
+
+            // Create transactions with varying priorities. All have nonce 1 to be "Ready".
+            let tx_high_priority = Transaction::new("alice".to_string(), 1, 200, vec![1]);
+            let tx_medium_priority = Transaction::new("bob".to_string(), 1, 150, vec![2]);
+            let tx_low_priority = Transaction::new("charlie".to_string(), 1, 100, vec![3]);
+
+            // Create a pending transaction with a high priority. Nonce 2 is missing, so
+            // this should NOT be included in the block because its status is not "Ready".
+            let tx_pending = Transaction::new("alice".to_string(), 3, 999, vec![4]);
+
+            // Submit all transactions to the pool.
+            pool.submit_transaction(tx_high_priority.clone()).unwrap();
+            pool.submit_transaction(tx_medium_priority.clone()).unwrap();
+            pool.submit_transaction(tx_low_priority.clone()).unwrap();
+            pool.submit_transaction(tx_pending.clone()).unwrap();
+
+            // Initial sanity check: 3 transactions should be ready for inclusion.
+            assert_eq!(pool.get_ready_count(), 3, "There should be 3 ready transactions before building the block");
+
+            // --- ACT ---
+            // Build a block with a limit of 2 transactions.
+            let block = pool.build_block(2);
+
+            // --- ASSERT ---
+            // 1. Test: Transaction limit per block.
+            // The block should contain exactly 2 transactions.
+            assert_eq!(block.len(), 2, "Block should be limited to 2 transactions");
 
             // 2. Test: Priority-based ordering.
             // The block should contain the transactions with the highest priority, in order.
@@ -301,8 +1452,915 @@ mod tests {
 
             // Final state check: The pool should now contain the two remaining transactions.
             assert_eq!(pool.get_total_count(), 2, "Pool should have 2 transactions remaining");
-            // After including 'alice's' nonce 1, 'tx_pending' (nonce 2) should now be ready.
-            assert_eq!(pool.get_ready_count(), 2, "The 2 remaining transactions should now be ready");
+            // 'tx_pending' (nonce 3) still waits for 'alice's' nonce 2.
+            assert_eq!(pool.get_ready_count(), 1, "Only charlie's transaction should be ready");
+            assert_eq!(pool.get_transaction(&tx_pending.hash).unwrap().status, PoolStatus::Pending);
+        }
+
+    #[test]
+    fn build_block_skips_transactions_over_the_weight_limit() {
+        let mut pool = TransactionPool::new(10);
+        let heavy = Transaction::new("alice".to_string(), 1, 100, vec![]);
+        let after_heavy = Transaction::new("alice".to_string(), 2, 100, vec![]);
+        let light = Transaction::new("bob".to_string(), 1, 10, vec![]);
+        let proof_heavy = Transaction::new("charlie".to_string(), 1, 50, vec![]);
+        pool.submit_pool_transaction(PoolTransaction::new(heavy.clone()).with_weight(Weight::from_parts(900, 0))).unwrap();
+        pool.submit_pool_transaction(PoolTransaction::new(after_heavy.clone()).with_weight(Weight::from_parts(10, 0))).unwrap();
+        pool.submit_pool_transaction(PoolTransaction::new(light.clone()).with_weight(Weight::from_parts(300, 10))).unwrap();
+        pool.submit_pool_transaction(PoolTransaction::new(proof_heavy.clone()).with_weight(Weight::from_parts(1, 200))).unwrap();
+
+        let summary = pool.build_block_with_limits(&BlockLimits::new(Weight::from_parts(500, 100), usize::MAX));
+        let hashes: Vec<_> = summary.transactions.iter().map(|tx| tx.hash).collect();
+        // alice's nonce 2 would fit, but cannot go in without her skipped nonce 1
+        assert_eq!(hashes, vec![light.hash]);
+        assert_eq!(summary.skipped, vec![heavy.hash, proof_heavy.hash]);
+        assert_eq!(summary.consumed_weight, Weight::from_parts(300, 10));
+        assert_eq!(summary.consumed_length, light.encoded_len());
+
+        assert_eq!(pool.get_total_count(), 3);
+        assert_eq!(pool.get_transaction(&after_heavy.hash).unwrap().status, PoolStatus::Ready);
+    }
+
+    #[test]
+    fn build_block_skips_transactions_over_the_length_limit() {
+        let mut pool = TransactionPool::new(10);
+        let small = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let large = Transaction::new("bob".to_string(), 1, 100, vec![0; 64]);
+        let small2 = Transaction::new("charlie".to_string(), 1, 5, vec![1]);
+        for tx in [&small, &large, &small2] {
+            pool.submit_transaction(tx.clone()).unwrap();
+        }
+        assert_eq!(small.encoded_len(), 23);
+
+        let max_length = small.encoded_len() + small2.encoded_len();
+        let summary = pool.build_block_with_limits(&BlockLimits::new(Weight::from_parts(u64::MAX, u64::MAX), max_length));
+        let hashes: Vec<_> = summary.transactions.iter().map(|tx| tx.hash).collect();
+        assert_eq!(hashes, vec![small.hash, small2.hash]);
+        assert_eq!(summary.skipped, vec![large.hash]);
+        assert_eq!(summary.consumed_length, max_length);
+        assert_eq!(summary.consumed_weight, Weight::from_parts(2 * BASE_TRANSACTION_WEIGHT.ref_time, 0));
+
+        // The limits and the transaction count both apply
+        let limits = BlockLimits::unbounded().with_max_transactions(0);
+        assert!(pool.build_block_with_limits(&limits).transactions.is_empty());
+        assert_eq!(pool.build_block(1), vec![large]);
+    }
+
+    #[test]
+    fn transaction_hash_known_answer() {
+        let tx = Transaction::new("alice".to_string(), 1, 100, vec![1, 2, 3]);
+        assert_eq!(to_hex(&tx.encode()), "0x14616c696365010000000000000064000000000000000c010203");
+        assert_eq!(to_hex(&tx.hash), "0x9c4bb78b66a48acbb1a41f0c1a58c7d3c173cf144f12a156085b85277c3578a2");
+    }
+
+    #[test]
+    fn transaction_hash_covers_every_field() {
+        let long_sender = "a".repeat(40);
+        let base = Transaction::new(long_sender.clone(), 1, 100, vec![1]);
+        let variants = [
+            Transaction::new(format!("{}b", long_sender), 1, 100, vec![1]),
+            Transaction::new(long_sender.clone(), 2, 100, vec![1]),
+            Transaction::new(long_sender.clone(), 1, 101, vec![1]),
+            Transaction::new(long_sender.clone(), 1, 100, vec![2]),
+            Transaction::new(long_sender, 1, 100, vec![1, 0]),
+        ];
+        for variant in &variants {
+            assert_ne!(variant.hash, base.hash);
+        }
+
+        let mut pool = TransactionPool::new(10);
+        assert_eq!(pool.submit_transaction(base.clone()), Ok(()));
+        assert_eq!(pool.submit_transaction(variants[1].clone()), Ok(()));
+        assert_eq!(pool.get_transaction(&variants[1].hash).unwrap().transaction.nonce, 2);
+        assert_eq!(pool.submit_transaction(base), Err(Error::TransactionDuplicate));
+    }
+
+    #[test]
+    fn submit_transaction_with_forged_hash_fail() {
+        let mut pool = TransactionPool::new(10);
+        let mut tx = Transaction::new("alice".to_string(), 1, 100, vec![1, 2, 3]);
+        tx.data = vec![9];
+        assert_eq!(pool.submit_transaction(tx), Err(Error::InvalidHash));
+        assert_eq!(pool.get_total_count(), 0);
+    }
+
+    #[test]
+    fn higher_priority_replaces_same_sender_and_nonce() {
+        let mut pool = TransactionPool::new(2);
+        let original = Transaction::new("alice".to_string(), 1, 100, vec![1]);
+        let next = Transaction::new("alice".to_string(), 2, 100, vec![2]);
+        let bump = Transaction::new("alice".to_string(), 1, 150, vec![1]);
+        pool.submit_transaction(original.clone()).unwrap();
+        pool.submit_transaction(next.clone()).unwrap();
+
+        // The pool is full, but a replacement does not need a free slot
+        assert_eq!(pool.submit_transaction(bump.clone()), Ok(()));
+        assert_eq!(pool.take_events(), vec![PoolEvent::Replaced { old: original.hash, new: bump.hash }]);
+        assert!(pool.get_transaction(&original.hash).is_none());
+        assert_eq!(pool.get_total_count(), 2);
+        assert_eq!(pool.get_ready_count(), 2);
+
+        let block = pool.build_block(10);
+        let hashes: Vec<_> = block.iter().map(|tx| tx.hash).collect();
+        assert_eq!(hashes, vec![bump.hash, next.hash]);
+    }
+
+    #[test]
+    fn lower_or_equal_priority_replacement_fail() {
+        let mut pool = TransactionPool::new(10);
+        let original = Transaction::new("alice".to_string(), 1, 100, vec![1]);
+        pool.submit_transaction(original.clone()).unwrap();
+
+        let equal = Transaction::new("alice".to_string(), 1, 100, vec![2]);
+        let lower = Transaction::new("alice".to_string(), 1, 99, vec![3]);
+        assert_eq!(pool.submit_transaction(equal), Err(Error::TooLowPriority));
+        assert_eq!(pool.submit_transaction(lower), Err(Error::TooLowPriority));
+        assert!(pool.take_events().is_empty());
+        assert_eq!(pool.get_total_count(), 1);
+        assert!(pool.get_transaction(&original.hash).is_some());
+    }
+
+    #[test]
+    fn full_pool_evicts_lowest_future_transaction_first() {
+        let mut pool = TransactionPool::new(3);
+        let ready_low = Transaction::new("alice".to_string(), 1, 5, vec![]);
+        let future_low = Transaction::new("bob".to_string(), 3, 20, vec![]);
+        let future_high = Transaction::new("charlie".to_string(), 2, 90, vec![]);
+        for tx in [&ready_low, &future_low, &future_high] {
+            pool.submit_transaction(tx.clone()).unwrap();
+        }
+
+        let newcomer = Transaction::new("dave".to_string(), 1, 50, vec![]);
+        assert_eq!(pool.submit_transaction(newcomer.clone()), Ok(()));
+        assert_eq!(pool.take_events(), vec![PoolEvent::Evicted { hash: future_low.hash, by: newcomer.hash }]);
+        assert!(pool.get_transaction(&future_low.hash).is_none());
+
+        // No future transaction is cheaper than 60 any more, so the cheapest ready one goes
+        let second = Transaction::new("erin".to_string(), 1, 60, vec![]);
+        assert_eq!(pool.submit_transaction(second.clone()), Ok(()));
+        assert_eq!(pool.take_events(), vec![PoolEvent::Evicted { hash: ready_low.hash, by: second.hash }]);
+        assert_eq!(pool.get_total_count(), 3);
+    }
+
+    #[test]
+    fn full_pool_rejects_newcomer_that_does_not_outbid() {
+        let mut pool = TransactionPool::new(2);
+        pool.submit_transaction(Transaction::new("alice".to_string(), 1, 50, vec![])).unwrap();
+        pool.submit_transaction(Transaction::new("bob".to_string(), 1, 50, vec![])).unwrap();
+        let result = pool.submit_transaction(Transaction::new("charlie".to_string(), 1, 50, vec![]));
+        assert_eq!(result, Err(Error::TransactionPoolFull));
+        assert!(pool.take_events().is_empty());
+    }
+
+    #[test]
+    fn evicting_a_dependency_demotes_its_dependants() {
+        let mut pool = TransactionPool::new(2);
+        let first = Transaction::new("alice".to_string(), 1, 1, vec![]);
+        let second = Transaction::new("alice".to_string(), 2, 40, vec![]);
+        pool.submit_transaction(first.clone()).unwrap();
+        pool.submit_transaction(second.clone()).unwrap();
+
+        pool.submit_transaction(Transaction::new("bob".to_string(), 1, 10, vec![])).unwrap();
+        assert!(pool.get_transaction(&first.hash).is_none());
+        assert_eq!(pool.get_transaction(&second.hash).unwrap().status, PoolStatus::Pending);
+    }
+
+    #[test]
+    fn per_sender_limit() {
+        let mut pool = TransactionPool::new(10).with_max_per_sender(2);
+        pool.submit_transaction(Transaction::new("alice".to_string(), 1, 10, vec![])).unwrap();
+        pool.submit_transaction(Transaction::new("alice".to_string(), 2, 10, vec![])).unwrap();
+        let third = Transaction::new("alice".to_string(), 3, 1_000, vec![]);
+        assert_eq!(pool.submit_transaction(third), Err(Error::SenderLimitReached));
+
+        // Replacing an existing nonce does not count against the limit
+        assert_eq!(pool.submit_transaction(Transaction::new("alice".to_string(), 2, 20, vec![])), Ok(()));
+        assert_eq!(pool.submit_transaction(Transaction::new("bob".to_string(), 1, 10, vec![])), Ok(()));
+    }
+
+    #[test]
+    fn on_new_block_drops_expired_transactions() {
+        let mut pool = TransactionPool::new(10);
+        let first = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let second = Transaction::new("alice".to_string(), 2, 10, vec![]);
+        let short_lived = Transaction::new("bob".to_string(), 1, 10, vec![]);
+        pool.on_new_block(5);
+        pool.submit_pool_transaction(PoolTransaction::new(first.clone()).with_longevity(3)).unwrap();
+        pool.submit_transaction(second.clone()).unwrap();
+        pool.submit_pool_transaction(PoolTransaction::new(short_lived.clone()).with_longevity(1)).unwrap();
+        assert_eq!(pool.get_transaction(&first.hash).unwrap().inserted_at, 5);
+
+        pool.on_new_block(6);
+        assert_eq!(pool.take_events(), vec![PoolEvent::Dropped { hash: short_lived.hash, reason: DropReason::Expired }]);
+
+        pool.on_new_block(7);
+        assert!(pool.take_events().is_empty());
+        pool.on_new_block(8);
+        assert_eq!(pool.take_events(), vec![PoolEvent::Dropped { hash: first.hash, reason: DropReason::Expired }]);
+
+        // alice's nonce 2 outlives nonce 1 but now waits for it again
+        assert_eq!(pool.get_total_count(), 1);
+        assert_eq!(pool.get_transaction(&second.hash).unwrap().status, PoolStatus::Pending);
+    }
+
+    #[test]
+    fn dropped_transactions_are_banned_temporarily() {
+        let mut pool = TransactionPool::new(10);
+        let tx = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        pool.submit_pool_transaction(PoolTransaction::new(tx.clone()).with_longevity(2)).unwrap();
+        pool.on_new_block(2);
+        assert!(pool.is_banned(&tx.hash));
+        assert_eq!(pool.submit_transaction(tx.clone()), Err(Error::TemporarilyBanned));
+
+        pool.on_new_block(2 + BAN_DURATION - 1);
+        assert_eq!(pool.submit_transaction(tx.clone()), Err(Error::TemporarilyBanned));
+        pool.on_new_block(2 + BAN_DURATION);
+        assert!(!pool.is_banned(&tx.hash));
+        assert_eq!(pool.submit_transaction(tx), Ok(()));
+    }
+
+    #[test]
+    fn bans_near_the_last_block_number_do_not_overflow() {
+        let mut pool = TransactionPool::new(10);
+        let tx = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        pool.submit_transaction(tx.clone()).unwrap();
+        pool.on_new_block(u64::MAX - 1);
+        assert!(pool.is_banned(&tx.hash));
+        assert_eq!(pool.get_total_count(), 0);
+    }
+
+    #[test]
+    fn newcomers_do_not_evict_their_own_dependencies() {
+        let mut pool = TransactionPool::new(2);
+        let alice1 = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let bob1 = Transaction::new("bob".to_string(), 1, 20, vec![]);
+        pool.submit_transaction(alice1.clone()).unwrap();
+        pool.submit_transaction(bob1.clone()).unwrap();
+
+        // alice's nonce 2 outbids her nonce 1 but needs it, so bob's transaction goes instead
+        let alice2 = Transaction::new("alice".to_string(), 2, 50, vec![]);
+        pool.submit_transaction(alice2.clone()).unwrap();
+        assert_eq!(pool.take_events(), vec![PoolEvent::Evicted { hash: bob1.hash, by: alice2.hash }]);
+        assert_eq!(pool.get_transaction(&alice2.hash).unwrap().status, PoolStatus::Ready);
+
+        // Providers of required tags are kept too; with nothing else below it, the newcomer is refused
+        let mut pool = TransactionPool::new(1);
+        let provider = Transaction::new("carol".to_string(), 1, 10, vec![]);
+        pool.submit_transaction_with_tags(provider.clone(), vec![b"feed".to_vec()], vec![]).unwrap();
+        let consumer = Transaction::new("dave".to_string(), 1, 50, vec![]);
+        let result = pool.submit_transaction_with_tags(consumer, vec![], vec![b"feed".to_vec()]);
+        assert_eq!(result, Err(Error::TransactionPoolFull));
+        assert!(pool.get_transaction(&provider.hash).is_some());
+    }
+
+    #[test]
+    fn evicted_and_stale_transactions_are_banned() {
+        let mut pool = TransactionPool::new(1);
+        let cheap = Transaction::new("alice".to_string(), 1, 1, vec![]);
+        pool.submit_transaction(cheap.clone()).unwrap();
+        pool.submit_transaction(Transaction::new("bob".to_string(), 1, 10, vec![])).unwrap();
+        assert!(pool.is_banned(&cheap.hash));
+
+        pool.build_block(1);
+        let stale = Transaction::new("bob".to_string(), 1, 20, vec![]);
+        assert_eq!(pool.submit_transaction(stale.clone()), Err(Error::InvalidNonce));
+        assert_eq!(pool.submit_transaction(stale), Err(Error::TemporarilyBanned));
+    }
+
+    #[test]
+    fn maintain_reinjects_retracted_transactions() {
+        let mut pool = TransactionPool::new(10);
+        let alice1 = Transaction::new("alice".to_string(), 1, 30, vec![]);
+        let alice2 = Transaction::new("alice".to_string(), 2, 30, vec![]);
+        let alice3 = Transaction::new("alice".to_string(), 3, 30, vec![]);
+        let bob1 = Transaction::new("bob".to_string(), 1, 20, vec![]);
+        for tx in [&alice1, &alice2, &alice3, &bob1] {
+            pool.submit_transaction(tx.clone()).unwrap();
+        }
+        let block = pool.build_block(3);
+        assert_eq!(block, vec![alice1.clone(), alice2.clone(), alice3.clone()]);
+        assert_eq!(pool.get_sender_next_expected_nonce("alice"), 4);
+
+        pool.maintain(&[], &[block]);
+        assert_eq!(pool.get_sender_next_expected_nonce("alice"), 1);
+        assert_eq!(pool.get_total_count(), 4);
+        assert_eq!(pool.get_ready_count(), 4);
+        assert!(pool.take_events().is_empty());
+        assert_eq!(pool.build_block(10), vec![alice1, alice2, alice3, bob1]);
+    }
+
+    #[test]
+    fn maintain_prunes_enacted_and_stale_transactions() {
+        let mut pool = TransactionPool::new(10);
+        let ours = Transaction::new("alice".to_string(), 1, 30, vec![1]);
+        let next = Transaction::new("alice".to_string(), 2, 30, vec![]);
+        let shared = Transaction::new("bob".to_string(), 1, 20, vec![]);
+        for tx in [&ours, &next, &shared] {
+            pool.submit_transaction(tx.clone()).unwrap();
         }
+
+        // Another author's block used alice's nonce 1 for a different transaction
+        let theirs = Transaction::new("alice".to_string(), 1, 5, vec![2]);
+        pool.maintain(&[vec![theirs, shared.clone()]], &[]);
+        assert_eq!(pool.take_events(), vec![PoolEvent::Dropped { hash: ours.hash, reason: DropReason::Stale }]);
+        assert!(pool.get_transaction(&shared.hash).is_none());
+        assert_eq!(pool.get_sender_next_expected_nonce("alice"), 2);
+        assert_eq!(pool.get_sender_next_expected_nonce("bob"), 2);
+        assert_eq!(pool.get_total_count(), 1);
+        assert_eq!(pool.build_block(10), vec![next]);
+    }
+
+    #[test]
+    fn maintain_switches_forks() {
+        let mut pool = TransactionPool::new(10);
+        let alice1 = Transaction::new("alice".to_string(), 1, 30, vec![]);
+        let carol1 = Transaction::new("carol".to_string(), 1, 20, vec![]);
+        pool.submit_transaction(alice1.clone()).unwrap();
+        pool.submit_transaction(carol1.clone()).unwrap();
+        let ours = pool.build_block(10);
+
+        let dave1 = Transaction::new("dave".to_string(), 1, 10, vec![]);
+        pool.maintain(&[vec![alice1.clone(), dave1]], &[ours]);
+        assert_eq!(pool.get_total_count(), 1);
+        assert_eq!(pool.get_transaction(&carol1.hash).unwrap().status, PoolStatus::Ready);
+        assert_eq!(pool.get_sender_next_expected_nonce("alice"), 2);
+        assert_eq!(pool.get_sender_next_expected_nonce("carol"), 1);
+        assert_eq!(pool.get_sender_next_expected_nonce("dave"), 2);
+
+        // Enacting a block the pool already counts as on chain changes nothing
+        pool.maintain(&[vec![alice1]], &[]);
+        assert_eq!(pool.get_sender_next_expected_nonce("alice"), 2);
+        assert!(pool.take_events().is_empty());
+    }
+
+    #[test]
+    fn retracting_a_provider_demotes_its_dependants() {
+        let mut pool = TransactionPool::new(10);
+        let provider = Transaction::new("alice".to_string(), 1, 10, vec![1]);
+        pool.submit_transaction_with_tags(provider.clone(), vec![nonce_tag("alice", 1), b"feed".to_vec()], vec![]).unwrap();
+        let block = pool.build_block(10);
+        let consumer = Transaction::new("bob".to_string(), 1, 10, vec![]);
+        pool.submit_transaction_with_tags(consumer.clone(), vec![], vec![b"feed".to_vec()]).unwrap();
+        assert_eq!(pool.get_transaction(&consumer.hash).unwrap().status, PoolStatus::Ready);
+
+        // The new chain used alice's nonce 1 without providing the tag, so the provider cannot return
+        let replacement = Transaction::new("alice".to_string(), 1, 10, vec![2]);
+        pool.maintain(&[vec![replacement]], &[block]);
+        assert!(pool.get_transaction(&provider.hash).is_none());
+        assert_eq!(pool.get_transaction(&consumer.hash).unwrap().status, PoolStatus::Pending);
+        assert!(pool.build_block(10).is_empty());
     }
 
+    #[test]
+    fn transactions_in_both_forks_keep_their_validated_tags() {
+        let mut pool = TransactionPool::new(10);
+        let provider = Transaction::new("alice".to_string(), 1, 10, vec![1]);
+        pool.submit_transaction_with_tags(provider.clone(), vec![nonce_tag("alice", 1), b"feed".to_vec()], vec![]).unwrap();
+        let ours = pool.build_block(10);
+        let consumer = Transaction::new("bob".to_string(), 1, 10, vec![]);
+        pool.submit_transaction_with_tags(consumer.clone(), vec![], vec![b"feed".to_vec()]).unwrap();
+
+        let theirs = vec![Transaction::new("carol".to_string(), 1, 10, vec![]), provider];
+        pool.maintain(&[theirs], &[ours]);
+        assert_eq!(pool.get_transaction(&consumer.hash).unwrap().status, PoolStatus::Ready);
+        assert_eq!(pool.build_block(10), vec![consumer]);
+    }
+
+    #[tokio::test]
+    async fn watched_transaction_goes_from_future_to_finalized() {
+        let pool = WatchedPool::new(TransactionPool::new(10));
+        let second = Transaction::new("alice".to_string(), 2, 10, vec![]);
+        let statuses = pool.submit_and_watch(second).await.unwrap();
+        pool.submit(Transaction::new("alice".to_string(), 1, 10, vec![])).await.unwrap();
+
+        let block = pool.build_block(1, &BlockLimits::unbounded()).await;
+        assert_eq!(block.transactions.len(), 2);
+        pool.finalize(1).await;
+        assert_eq!(
+            statuses.collect::<Vec<_>>().await,
+            vec![TransactionStatus::Future, TransactionStatus::Ready, TransactionStatus::InBlock(1), TransactionStatus::Finalized(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn finalizing_the_last_block_number_takes_every_block() {
+        let pool = WatchedPool::new(TransactionPool::new(10));
+        let statuses = pool.submit_and_watch(Transaction::new("alice".to_string(), 1, 10, vec![])).await.unwrap();
+        pool.build_block(u64::MAX, &BlockLimits::unbounded()).await;
+        pool.finalize(u64::MAX).await;
+        assert_eq!(
+            statuses.collect::<Vec<_>>().await,
+            vec![TransactionStatus::Ready, TransactionStatus::InBlock(u64::MAX), TransactionStatus::Finalized(u64::MAX)]
+        );
+    }
+
+    #[tokio::test]
+    async fn watched_transactions_follow_a_reorg() {
+        let pool = WatchedPool::new(TransactionPool::new(10));
+        let alice1 = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let bob1 = Transaction::new("bob".to_string(), 1, 10, vec![1]);
+        let mut alice_statuses = pool.submit_and_watch(alice1.clone()).await.unwrap();
+        let bob_statuses = pool.submit_and_watch(bob1).await.unwrap();
+        let ours = pool.build_block(1, &BlockLimits::unbounded()).await;
+
+        // The other fork's block 1 uses bob's nonce for a different transaction
+        let theirs = Block { number: 1, transactions: vec![Transaction::new("bob".to_string(), 1, 10, vec![2])] };
+        pool.maintain(&[theirs], &[ours]).await;
+        assert_eq!(
+            bob_statuses.collect::<Vec<_>>().await,
+            vec![TransactionStatus::Ready, TransactionStatus::InBlock(1), TransactionStatus::Retracted(1), TransactionStatus::Invalid]
+        );
+
+        pool.build_block(2, &BlockLimits::unbounded()).await;
+        pool.finalize(1).await;
+        for expected in [
+            TransactionStatus::Ready,
+            TransactionStatus::InBlock(1),
+            TransactionStatus::Retracted(1),
+            TransactionStatus::Ready,
+            TransactionStatus::InBlock(2),
+        ] {
+            assert_eq!(alice_statuses.next().await, Some(expected));
+        }
+        pool.finalize(2).await;
+        assert_eq!(alice_statuses.next().await, Some(TransactionStatus::Finalized(2)));
+        assert_eq!(alice_statuses.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn watched_transactions_report_usurped_and_dropped() {
+        let pool = WatchedPool::new(TransactionPool::new(10));
+        let original = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let bump = Transaction::new("alice".to_string(), 1, 20, vec![]);
+        let original_statuses = pool.submit_and_watch(original).await.unwrap();
+        pool.submit(bump.clone()).await.unwrap();
+        assert_eq!(
+            original_statuses.collect::<Vec<_>>().await,
+            vec![TransactionStatus::Ready, TransactionStatus::Usurped(bump.hash)]
+        );
+
+        pool.on_new_block(DEFAULT_LONGEVITY).await;
+        let expired = Transaction::new("alice".to_string(), 1, 30, vec![]);
+        let expired_statuses = pool.submit_and_watch(expired.clone()).await.unwrap();
+        pool.on_new_block(2 * DEFAULT_LONGEVITY).await;
+        assert_eq!(expired_statuses.collect::<Vec<_>>().await, vec![TransactionStatus::Ready, TransactionStatus::Dropped]);
+
+        assert_eq!(pool.submit_and_watch(expired).await.err(), Some(Error::TemporarilyBanned));
+    }
+
+    #[tokio::test]
+    async fn statuses_stream_to_another_task() {
+        let pool = WatchedPool::new(TransactionPool::new(10));
+        let statuses = pool.submit_and_watch(Transaction::new("alice".to_string(), 1, 10, vec![])).await.unwrap();
+        let watcher = tokio::spawn(async move { statuses.collect::<Vec<_>>().await });
+
+        let producer = pool.clone();
+        tokio::spawn(async move {
+            producer.build_block(1, &BlockLimits::unbounded()).await;
+            producer.finalize(1).await;
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            watcher.await.unwrap(),
+            vec![TransactionStatus::Ready, TransactionStatus::InBlock(1), TransactionStatus::Finalized(1)]
+        );
+    }
+
+    #[test]
+    fn signature_validator_checks_the_signed_payload() {
+        let alice = Keypair::from_seed(b"alice");
+        let validator = SignatureValidator::new().with_account("alice", alice.public());
+        let mut pool = TransactionPool::new(10).with_validator(validator);
+
+        let signed = Transaction::new("alice".to_string(), 1, 10, vec![1]).signed(&alice);
+        assert_eq!(pool.submit_transaction(signed.clone()), Ok(()));
+
+        let unsigned = Transaction::new("alice".to_string(), 2, 10, vec![]);
+        assert_eq!(pool.submit_transaction(unsigned.clone()), Err(Error::Invalid(InvalidTransaction::BadProof)));
+        assert!(!pool.is_banned(&unsigned.hash));
+
+        let mut tampered = Transaction::new("alice".to_string(), 3, 10, vec![]).signed(&alice);
+        tampered.data = vec![9];
+        tampered.hash = tampered.compute_hash();
+        assert_eq!(pool.submit_transaction(tampered), Err(Error::Invalid(InvalidTransaction::BadProof)));
+        let forged = Transaction::new("alice".to_string(), 4, 10, vec![]).signed(&Keypair::from_seed(b"mallory"));
+        assert_eq!(pool.submit_transaction(forged), Err(Error::Invalid(InvalidTransaction::BadProof)));
+
+        // Unknown senders are not banned: their key may be registered later
+        let bob = Transaction::new("bob".to_string(), 1, 10, vec![]).signed(&Keypair::from_seed(b"bob"));
+        assert_eq!(pool.submit_transaction(bob.clone()), Err(Error::Unknown(UnknownTransaction::CannotLookup)));
+        assert!(!pool.is_banned(&bob.hash));
+        let oracle = Transaction::from_unsigned(&UnsignedTransaction::new("price:42", 1, 1), 50);
+        assert_eq!(pool.submit_transaction(oracle), Err(Error::Unknown(UnknownTransaction::NoUnsignedValidator)));
+
+        assert_eq!(pool.get_total_count(), 1);
+        assert_eq!(pool.build_block(10), vec![signed]);
+    }
+
+    #[test]
+    fn badly_signed_copies_do_not_block_the_genuine_transaction() {
+        let alice = Keypair::from_seed(b"alice");
+        let mut pool = TransactionPool::new(10).with_validator(SignatureValidator::new().with_account("alice", alice.public()));
+        let genuine = Transaction::new("alice".to_string(), 1, 10, vec![1]).signed(&alice);
+
+        let copy = genuine.clone().signed(&Keypair::from_seed(b"mallory"));
+        assert_eq!(copy.hash, genuine.hash);
+        assert_eq!(pool.submit_transaction(copy), Err(Error::Invalid(InvalidTransaction::BadProof)));
+        assert_eq!(pool.submit_transaction(genuine), Ok(()));
+    }
+
+    #[test]
+    fn forged_hashes_do_not_ban_the_real_transaction() {
+        let alice = Keypair::from_seed(b"alice");
+        let mut pool = TransactionPool::new(10).with_validator(SignatureValidator::new().with_account("alice", alice.public()));
+        let genuine = Transaction::new("alice".to_string(), 1, 10, vec![1]).signed(&alice);
+
+        let mut forged = Transaction::new("alice".to_string(), 1, 10, vec![2]);
+        forged.hash = genuine.hash;
+        assert_eq!(pool.submit_transaction(forged), Err(Error::InvalidHash));
+        assert!(!pool.is_banned(&genuine.hash));
+        assert_eq!(pool.submit_transaction(genuine), Ok(()));
+    }
+
+    /// Ranks by payload size and lets senders be revoked after submission.
+    struct SizeValidator {
+        revoked: Arc<Mutex<HashSet<String>>>,
+    }
+
+    impl TransactionValidator for SizeValidator {
+        fn validate_transaction(&self, transaction: &Transaction) -> TransactionValidity {
+            if self.revoked.lock().unwrap().contains(&transaction.sender) {
+                return Err(TransactionValidityError::Invalid(InvalidTransaction::Custom(1)));
+            }
+            Ok(ValidTransaction { priority: transaction.data.len() as u64, longevity: 5, ..ValidTransaction::default_for(transaction) })
+        }
+    }
+
+    #[test]
+    fn validity_sets_priority_and_longevity() {
+        let mut pool = TransactionPool::new(10).with_validator(SizeValidator { revoked: Arc::default() });
+        let small = Transaction::new("alice".to_string(), 1, 1_000, vec![1]);
+        let large = Transaction::new("bob".to_string(), 1, 1, vec![1, 2, 3]);
+        pool.submit_transaction(small.clone()).unwrap();
+        pool.submit_transaction(large.clone()).unwrap();
+        assert_eq!(pool.get_transaction(&small.hash).unwrap().priority, 1);
+        assert_eq!(pool.get_transaction(&small.hash).unwrap().longevity, 5);
+
+        // A larger payload for the same nonce outbids the pooled one whatever its own priority
+        let bump = Transaction::new("alice".to_string(), 1, 0, vec![1, 2]);
+        assert_eq!(pool.submit_transaction(bump.clone()), Ok(()));
+        assert_eq!(pool.build_block(10), vec![large, bump]);
+    }
+
+    #[test]
+    fn revalidate_drops_transactions_that_became_invalid() {
+        let revoked = Arc::new(Mutex::new(HashSet::new()));
+        let mut pool = TransactionPool::new(10).with_validator(SizeValidator { revoked: revoked.clone() });
+        let alice1 = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let bob1 = Transaction::new("bob".to_string(), 1, 10, vec![]);
+        pool.submit_transaction(alice1.clone()).unwrap();
+        pool.submit_transaction(bob1.clone()).unwrap();
+
+        revoked.lock().unwrap().insert("alice".to_string());
+        pool.revalidate();
+        assert_eq!(pool.take_events(), vec![PoolEvent::Dropped { hash: alice1.hash, reason: DropReason::Invalid }]);
+        assert!(pool.is_banned(&alice1.hash));
+        assert_eq!(pool.get_total_count(), 1);
+        assert!(pool.get_transaction(&bob1.hash).is_some());
+    }
+
+    #[test]
+    fn default_tags_follow_sender_nonces() {
+        let pool_tx = PoolTransaction::new(Transaction::new("alice".to_string(), 2, 100, vec![]));
+        assert_eq!(pool_tx.provides, vec![nonce_tag("alice", 2)]);
+        assert_eq!(pool_tx.requires, vec![nonce_tag("alice", 1)]);
+        assert!(PoolTransaction::new(Transaction::new("alice".to_string(), 1, 100, vec![])).requires.is_empty());
+    }
+
+    #[test]
+    fn custom_tags_order_transactions_across_senders() {
+        let mut pool = TransactionPool::new(10);
+        let open_channel = Transaction::new("alice".to_string(), 1, 10, vec![1]);
+        let use_channel = Transaction::new("bob".to_string(), 1, 500, vec![2]);
+        let channel_tag = b"channel:7".to_vec();
+
+        pool.submit_transaction_with_tags(use_channel.clone(), vec![nonce_tag("bob", 1)], vec![channel_tag.clone()]).unwrap();
+        assert_eq!(pool.get_transaction(&use_channel.hash).unwrap().status, PoolStatus::Pending);
+
+        pool.submit_transaction_with_tags(open_channel.clone(), vec![nonce_tag("alice", 1), channel_tag], vec![]).unwrap();
+        assert_eq!(pool.get_transaction(&use_channel.hash).unwrap().status, PoolStatus::Ready);
+
+        // bob pays more but cannot be emitted before the transaction providing his requirement
+        let block = pool.build_block(10);
+        let hashes: Vec<_> = block.iter().map(|tx| tx.hash).collect();
+        assert_eq!(hashes, vec![open_channel.hash, use_channel.hash]);
+    }
+
+    #[test]
+    fn tags_provided_in_earlier_blocks_stay_satisfied() {
+        let mut pool = TransactionPool::new(10);
+        let provider = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        pool.submit_transaction_with_tags(provider, vec![b"feed".to_vec()], vec![]).unwrap();
+        assert_eq!(pool.build_block(1).len(), 1);
+
+        let consumer = Transaction::new("bob".to_string(), 1, 10, vec![]);
+        pool.submit_transaction_with_tags(consumer.clone(), vec![], vec![b"feed".to_vec()]).unwrap();
+        assert_eq!(pool.get_transaction(&consumer.hash).unwrap().status, PoolStatus::Ready);
+    }
+
+    #[test]
+    fn unsigned_transactions_participate_through_tags() {
+        let mut pool = TransactionPool::new(10);
+        let price = Transaction::from_unsigned(&UnsignedTransaction::new("price:42", 1, 1), 50);
+        let update = Transaction::from_unsigned(&UnsignedTransaction::new("price:43", 2, 2), 80);
+        assert!(price.is_unsigned());
+        assert_eq!(price.data, "price:42".encode());
+
+        pool.submit_transaction(price.clone()).unwrap();
+        pool.submit_transaction_with_tags(update.clone(), vec![b"price:2".to_vec()], vec![price.hash.to_vec()]).unwrap();
+        pool.submit_transaction(Transaction::new("alice".to_string(), 1, 60, vec![])).unwrap();
+        assert_eq!(pool.get_ready_count(), 3);
+
+        let block = pool.build_block(10);
+        let priorities: Vec<u64> = block.iter().map(|tx| tx.priority).collect();
+        assert_eq!(priorities, vec![60, 50, 80]);
+        assert_eq!(pool.get_sender_next_expected_nonce("alice"), 2);
+    }
+
+    #[test]
+    fn stats_count_queues_rejections_and_evictions() {
+        let mut pool = TransactionPool::new(3);
+        let alice_1 = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let alice_3 = Transaction::new("alice".to_string(), 3, 5, vec![]);
+        pool.submit_transaction(alice_1.clone()).unwrap();
+        pool.submit_transaction(alice_3.clone()).unwrap();
+        pool.on_new_block(2);
+        pool.submit_transaction(Transaction::new("bob".to_string(), 1, 100, vec![])).unwrap();
+        assert_eq!(pool.submit_transaction(alice_1), Err(Error::TransactionDuplicate));
+
+        // the pool is full, so carol evicts the lowest future transaction
+        pool.submit_transaction(Transaction::new("carol".to_string(), 1, 200, vec![])).unwrap();
+        assert_eq!(pool.submit_transaction(alice_3), Err(Error::TemporarilyBanned));
+        pool.on_new_block(5);
+
+        let stats = pool.stats();
+        assert_eq!((stats.ready, stats.future, stats.banned), (3, 0, 1));
+        let depths: Vec<(&str, usize)> = stats.sender_depths.iter().map(|(sender, depth)| (sender.as_str(), *depth)).collect();
+        assert_eq!(depths, vec![("alice", 1), ("bob", 1), ("carol", 1)]);
+        assert_eq!(stats.oldest_age, Some(5));
+        assert_eq!(stats.priority_histogram.into_iter().collect::<Vec<_>>(), vec![(8, 1), (64, 1), (128, 1)]);
+        assert_eq!(
+            stats.rejections,
+            HashMap::from([(Error::TransactionDuplicate, 1), (Error::TemporarilyBanned, 1)])
+        );
+        assert_eq!(stats.evictions, 1);
+
+        let empty = TransactionPool::new(1).stats();
+        assert_eq!(empty.oldest_age, None);
+        assert!(empty.priority_histogram.is_empty());
+    }
+
+    #[test]
+    fn maintain_resubmissions_are_not_counted_as_rejections() {
+        let mut pool = TransactionPool::new(10);
+        let alice_1 = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        pool.submit_transaction(alice_1.clone()).unwrap();
+        let retracted = pool.build_block(10);
+
+        // alice 1 is also in the new chain, so resubmitting it from the retracted block fails
+        pool.maintain(&[vec![alice_1]], &[retracted]);
+        assert!(pool.stats().rejections.is_empty());
+    }
+
+    #[test]
+    fn dump_lists_entries_in_inclusion_order() {
+        let mut pool = TransactionPool::new(10);
+        let open_channel = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let use_channel = Transaction::new("bob".to_string(), 1, 500, vec![]);
+        let carol_1 = Transaction::new("carol".to_string(), 1, 50, vec![]);
+        let dave_2 = Transaction::new("dave".to_string(), 2, 70, vec![]);
+        let channel_tag = b"channel:7".to_vec();
+        pool.submit_transaction_with_tags(use_channel.clone(), vec![nonce_tag("bob", 1)], vec![channel_tag.clone()]).unwrap();
+        pool.submit_transaction_with_tags(open_channel.clone(), vec![nonce_tag("alice", 1), channel_tag], vec![]).unwrap();
+        pool.submit_transaction(carol_1.clone()).unwrap();
+        pool.submit_transaction(dave_2.clone()).unwrap();
+
+        let dump = pool.dump();
+        let hashes: Vec<TransactionHash> = dump.iter().map(|entry| entry.hash).collect();
+        assert_eq!(hashes, vec![carol_1.hash, open_channel.hash, use_channel.hash, dave_2.hash]);
+        assert!(dump[..3].iter().all(|entry| entry.status == PoolStatus::Ready && entry.missing.is_empty()));
+        assert_eq!(dump[3].status, PoolStatus::Pending);
+        assert_eq!(dump[3].missing, vec![nonce_tag("dave", 1)]);
+
+        // dumping does not disturb the pool
+        let included: Vec<TransactionHash> = pool.build_block(10).iter().map(|tx| tx.hash).collect();
+        assert_eq!(included, hashes[..3]);
+    }
+
+    #[test]
+    fn pool_transaction_json_round_trip() {
+        let tx = Transaction::new("alice".to_string(), 1, 100, vec![1, 2, 3]);
+        let pool_tx = PoolTransaction::new(tx.clone());
+        let json = serde_json::to_string(&pool_tx).unwrap();
+        assert!(json.contains(&format!("\"hash\":\"{}\"", to_hex(&tx.hash))));
+        assert!(json.contains("\"data\":\"0x010203\""));
+        assert!(json.contains("\"status\":\"Pending\""));
+        assert!(json.contains("\"inserted_at\":0,\"longevity\":64"));
+        let decoded: PoolTransaction = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, pool_tx);
+
+        let signed = tx.signed(&Keypair::from_seed(b"alice"));
+        let json = serde_json::to_string(&signed).unwrap();
+        assert!(json.contains(&format!("\"signature\":\"{}\"", to_hex(&signed.signature.unwrap().to_bytes()))));
+        assert_eq!(serde_json::from_str::<Transaction>(&json).unwrap(), signed);
+    }
+
+    #[test]
+    fn transaction_from_json_fixture() {
+        let json = r#"{
+            "hash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "sender": "bob",
+            "nonce": 3,
+            "priority": 7,
+            "data": "0xff"
+        }"#;
+        let tx: Transaction = serde_json::from_str(json).unwrap();
+        assert_eq!(tx.hash[31], 1);
+        assert_eq!(tx.sender, "bob");
+        assert_eq!(tx.data, vec![0xff]);
+        assert!(serde_json::from_str::<Transaction>(&json.replace("0x0000", "0x00")).is_err());
+    }
+
+    #[test]
+    fn mutually_providing_transactions_lose_readiness_together() {
+        let mut pool = TransactionPool::new(10);
+        let opener = Transaction::new("alice".to_string(), 1, 10, vec![]);
+        let left = Transaction::new("bob".to_string(), 1, 10, vec![]);
+        let right = Transaction::new("charlie".to_string(), 1, 10, vec![]);
+        pool.submit_transaction_with_tags(opener.clone(), vec![b"x".to_vec()], vec![]).unwrap();
+        pool.submit_transaction_with_tags(left.clone(), vec![b"y".to_vec()], vec![b"x".to_vec()]).unwrap();
+        pool.submit_transaction_with_tags(right.clone(), vec![b"x".to_vec()], vec![b"y".to_vec()]).unwrap();
+        assert_eq!(pool.get_ready_count(), 3);
+
+        // right still provides x, but only because left is ready, which needs x
+        pool.submit_transaction(Transaction::new("alice".to_string(), 1, 20, vec![])).unwrap();
+        assert_eq!(pool.get_ready_count(), 1);
+        assert_eq!(pool.get_transaction(&left.hash).unwrap().status, PoolStatus::Pending);
+        assert_eq!(pool.get_transaction(&right.hash).unwrap().status, PoolStatus::Pending);
+    }
+
+    /// The pool before it was indexed: readiness is a fixed point over every transaction after
+    /// each submit, and blocks are built from a freshly sorted ready set.
+    struct RescanPool {
+        transactions: Vec<PoolTransaction>,
+        provided_on_chain: HashSet<Tag>,
+        sender_nonces: HashMap<String, u64>,
+    }
+
+    impl RescanPool {
+        fn new() -> Self {
+            Self { transactions: Vec::new(), provided_on_chain: HashSet::new(), sender_nonces: HashMap::new() }
+        }
+
+        fn submit(&mut self, transaction: Transaction) -> Result<(), Error> {
+            if self.transactions.iter().any(|pool_tx| pool_tx.transaction.hash == transaction.hash) {
+                return Err(Error::TransactionDuplicate);
+            }
+            if transaction.nonce <= self.sender_nonces.get(&transaction.sender).copied().unwrap_or(0) {
+                return Err(Error::InvalidNonce);
+            }
+            let same_nonce = self.transactions.iter().position(|pool_tx| {
+                pool_tx.transaction.sender == transaction.sender && pool_tx.transaction.nonce == transaction.nonce
+            });
+            if let Some(index) = same_nonce {
+                if transaction.priority <= self.transactions[index].transaction.priority {
+                    return Err(Error::TooLowPriority);
+                }
+                self.transactions.remove(index);
+            }
+            self.transactions.push(PoolTransaction::new(transaction));
+            self.update_ready_status();
+            Ok(())
+        }
+
+        fn update_ready_status(&mut self) {
+            let mut available: HashSet<Tag> = self.provided_on_chain.clone();
+            let mut ready: HashSet<TransactionHash> = HashSet::new();
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for pool_tx in &self.transactions {
+                    let hash = pool_tx.transaction.hash;
+                    if ready.contains(&hash) || !pool_tx.requires.iter().all(|tag| available.contains(tag)) {
+                        continue;
+                    }
+                    ready.insert(hash);
+                    available.extend(pool_tx.provides.iter().cloned());
+                    changed = true;
+                }
+            }
+            for pool_tx in &mut self.transactions {
+                pool_tx.status = if ready.contains(&pool_tx.transaction.hash) {PoolStatus::Ready} else {PoolStatus::Pending};
+            }
+        }
+
+        fn build_block(&mut self, max_transactions: usize) -> Vec<Transaction> {
+            let mut candidates: Vec<PoolTransaction> = self.transactions
+                .iter()
+                .filter(|pool_tx| pool_tx.can_be_included())
+                .cloned()
+                .collect();
+            candidates.sort_by(|a, b| {
+                let (a, b) = (&a.transaction, &b.transaction);
+                (Reverse(a.priority), &a.sender, a.nonce, a.hash).cmp(&(Reverse(b.priority), &b.sender, b.nonce, b.hash))
+            });
+            let mut block = Vec::new();
+            while block.len() < max_transactions {
+                let Some(index) = candidates
+                    .iter()
+                    .position(|pool_tx| pool_tx.requires.iter().all(|tag| self.provided_on_chain.contains(tag)))
+                else { break };
+                let pool_tx = candidates.remove(index);
+                self.provided_on_chain.extend(pool_tx.provides.iter().cloned());
+                self.sender_nonces.insert(pool_tx.transaction.sender.clone(), pool_tx.transaction.nonce);
+                self.transactions.retain(|other| other.transaction.hash != pool_tx.transaction.hash);
+                block.push(pool_tx.transaction);
+            }
+            self.update_ready_status();
+            block
+        }
+    }
+
+    /// Deterministic pseudo-random numbers below `bound`, so runs are reproducible.
+    fn next_random(seed: &mut u64, bound: u64) -> u64 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (*seed >> 33) % bound
+    }
+
+    fn random_transaction(seed: &mut u64, senders: u64, next_nonce: impl Fn(&str) -> u64) -> Transaction {
+        let sender = format!("sender{}", next_random(seed, senders));
+        let nonce = next_nonce(&sender) + next_random(seed, 4);
+        Transaction::new(sender, nonce, next_random(seed, 50), vec![next_random(seed, 3) as u8])
+    }
+
+    #[test]
+    fn indexed_pool_matches_full_rescan() {
+        let mut seed = 7;
+        let mut pool = TransactionPool::new(usize::MAX);
+        let mut reference = RescanPool::new();
+        for round in 0..2_000 {
+            if round % 50 == 49 {
+                let max = next_random(&mut seed, 20) as usize;
+                assert_eq!(pool.build_block(max), reference.build_block(max));
+                continue;
+            }
+            let tx = random_transaction(&mut seed, 8, |sender| pool.get_sender_next_expected_nonce(sender));
+            assert_eq!(pool.submit_transaction(tx.clone()), reference.submit(tx));
+            for pool_tx in &reference.transactions {
+                assert_eq!(pool.get_transaction(&pool_tx.transaction.hash).unwrap().status, pool_tx.status);
+            }
+            assert_eq!(pool.get_total_count(), reference.transactions.len());
+        }
+        assert_eq!(pool.build_block(usize::MAX), reference.build_block(usize::MAX));
+    }
+
+    /// Run with `cargo test --release pool_benchmark -- --ignored --nocapture`. The rescan
+    /// reference is quadratic, so the 20k data point takes a few minutes.
+    #[test]
+    #[ignore]
+    fn pool_benchmark() {
+        for size in [1_000, 4_000, 20_000] {
+            let mut seed = 1;
+            let transactions: Vec<Transaction> = (0..size)
+                .map(|i| Transaction::new(format!("sender{}", i % (size / 10)), i / (size / 10) + 1, next_random(&mut seed, 1_000), vec![]))
+                .collect();
+
+            let start = Instant::now();
+            let mut pool = TransactionPool::new(usize::MAX);
+            for tx in &transactions {
+                pool.submit_transaction(tx.clone()).unwrap();
+            }
+            let indexed_submit = start.elapsed();
+            let start = Instant::now();
+            let indexed_block = pool.build_block(size as usize);
+            let indexed_build = start.elapsed();
+
+            let start = Instant::now();
+            let mut reference = RescanPool::new();
+            for tx in &transactions {
+                reference.submit(tx.clone()).unwrap();
+            }
+            let rescan_submit = start.elapsed();
+            let start = Instant::now();
+            let rescan_block = reference.build_block(size as usize);
+            let rescan_build = start.elapsed();
+
+            assert_eq!(indexed_block, rescan_block);
+            println!(
+                "{size:>6} transactions: submit {indexed_submit:?} indexed vs {rescan_submit:?} rescan, \
+                 build_block {indexed_build:?} indexed vs {rescan_build:?} rescan"
+            );
+        }
+    }
+}
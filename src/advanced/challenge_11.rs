@@ -1,20 +1,87 @@
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+use serde::{Deserialize, Serialize};
+use crate::common::arithmetic::{ArithmeticError, BalanceArithmetic};
+use crate::common::codec::{decode_compact, encode_compact, read_byte, CodecError, Decode, Encode};
+use crate::common::serde_helpers::as_string;
+
+/// JSON: a bare number, e.g. `2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ChainId(pub u32);
 pub type Balance = u128;
 pub type AccountId = String;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub type LocalAssetId = u32;
+
+/// JSON: `"MainToken"`, `{"Local":5}` or `{"Foreign":{"chain":1,"id":5}}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum AssetId {
+    /// Native token, known to every chain without registration.
     MainToken,
+    /// Asset registered in this chain's registry.
+    Local(LocalAssetId),
+    /// Asset registered on another chain, identified by its home chain and local id there.
+    Foreign { chain: ChainId, id: LocalAssetId },
+}
+
+impl AssetId {
+    /// Location of the asset as seen from outside `chain`, used in outgoing messages.
+    pub fn to_global(self, chain: ChainId) -> AssetId {
+        match self {
+            AssetId::Local(id) => AssetId::Foreign { chain, id },
+            other => other,
+        }
+    }
+
+    /// Location of the asset as seen from `chain`, used for incoming messages.
+    pub fn to_local(self, chain: ChainId) -> AssetId {
+        match self {
+            AssetId::Foreign { chain: home, id } if home == chain => AssetId::Local(id),
+            other => other,
+        }
+    }
+}
+
+/// JSON: `{"symbol":"DOT","decimals":10,"min_balance":"1"}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssetMetadata {
+    pub symbol: String,
+    pub decimals: u8,
+    #[serde(with = "as_string")]
+    pub min_balance: Balance,
+}
+
+impl AssetMetadata {
+    pub fn new(symbol: &str, decimals: u8, min_balance: Balance) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            decimals,
+            min_balance,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+/// Registry entry: the owner manages roles, the admin mints and burns.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssetDetails {
+    pub owner: AccountId,
+    pub admin: AccountId,
+    pub metadata: AssetMetadata,
+}
+
+/// Unique among the messages sent by one chain.
+pub type MessageId = u64;
+
+/// JSON: `{"from_chain":1,"to_chain":2,"id":0,"sequence":0,"from_account":"alice","to_account":"bob","asset_id":"MainToken","amount":"10"}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TransferMessage {
     pub from_chain: ChainId,
     pub to_chain: ChainId,
+    pub id: MessageId,
+    /// Position of the message on the `(from_chain, to_chain)` channel, starting at 0.
+    pub sequence: u64,
     pub from_account: AccountId,
     pub to_account: AccountId,
     pub asset_id: AssetId,
+    #[serde(with = "as_string")]
     pub amount: Balance
 }
 
@@ -30,25 +97,182 @@ impl TransferMessage {
         Self {
             from_chain,
             to_chain,
+            id: 0,
+            sequence: 0,
             from_account,
             to_account,
             asset_id,
             amount
         }
     }
+
+    pub fn with_sequence(mut self, id: MessageId, sequence: u64) -> Self {
+        self.id = id;
+        self.sequence = sequence;
+        self
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+pub type TrapId = u64;
+
+/// Sent back to the origin chain when a transfer could not be deposited on its destination.
+///
+/// Without a `trap_id` the destination kept nothing and the origin refunds the sender;
+/// with one, the assets are held in the destination's asset trap until claimed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    pub transfer: TransferMessage,
+    pub error: Error,
+    pub trap_id: Option<TrapId>,
+}
+
+/// Assets that reached this chain but could not be deposited.
+///
+/// `error` is `None` for assets left in the holding register after an XCM program.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrappedAssets {
+    pub origin_chain: ChainId,
+    pub origin_account: AccountId,
+    pub asset_id: AssetId,
+    #[serde(with = "as_string")]
+    pub amount: Balance,
+    pub error: Option<Error>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Error {
     InsufficientBalance,
     InvalidDestinationChain,
     ZeroAmountTransfer,
+    UnknownAsset,
+    AssetAlreadyExists,
+    /// `MainToken` cannot be registered, and foreign ids cannot point at this chain.
+    InvalidAssetId,
+    NoPermission,
+    /// The resulting balance would be non-zero but below the asset's `min_balance`.
+    BelowMinimumBalance,
+    UnknownChain,
+    ChainAlreadyRegistered,
+    UnknownTrap,
+    /// An error report was delivered to a chain that did not send the original transfer.
+    InvalidReportDestination,
+    /// An error report came from a chain other than the transfer's destination.
+    InvalidReportOrigin,
+    /// The asset may not be teleported to this destination.
+    UntrustedTeleport,
+    /// Neither this chain nor the destination is the asset's reserve.
+    NotReserveTransferable,
+    /// Derivative supply on a chain does not match its sovereign account on the reserve.
+    SupplyMismatch,
+    /// Barrier: the origin chain is not on the allow-list.
+    OriginNotAllowed,
+    /// Barrier: the origin chain is not a trusted reserve for the asset.
+    UntrustedReserve,
+    /// Barrier: the message moves more than the per-message maximum.
+    AmountTooLarge,
+    /// Barrier: the origin chain used up its message quota for the current window.
+    RateLimited,
+    /// The message id or sequence number was already processed, or the message is already buffered.
+    DuplicateMessage,
+    /// The message skips ahead of the expected sequence number and cannot be buffered.
+    MessageOutOfOrder,
+    /// An error report names a transfer this chain did not send, or one already handled.
+    UnknownTransfer,
+    /// A balance or total supply would exceed `Balance::MAX`.
+    Overflow,
+    /// A balance would drop below zero.
+    Underflow,
+}
+
+impl From<ArithmeticError> for Error {
+    fn from(error: ArithmeticError) -> Self {
+        match error {
+            ArithmeticError::Overflow => Error::Overflow,
+            ArithmeticError::Underflow => Error::Underflow,
+        }
+    }
 }
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Account on this chain that holds the reserves backing derivatives minted on `chain`.
+pub fn sovereign_account(chain: ChainId) -> AccountId {
+    format!("sibling:{}", chain.0)
+}
+
+/// At most `max_messages` accepted from each origin chain per window of `window_blocks` blocks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub max_messages: u32,
+    pub window_blocks: u64,
+}
+
+/// Checks applied to every incoming message before it touches any balance.
+///
+/// The default configuration accepts everything, matching the behaviour before barriers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BarrierConfig {
+    /// `None` accepts any origin chain.
+    pub allowed_origins: Option<HashSet<ChainId>>,
+    /// Assets listed here are only accepted from the given reserve chains.
+    pub trusted_reserves: HashMap<AssetId, HashSet<ChainId>>,
+    pub max_amount_per_message: Option<Balance>,
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl BarrierConfig {
+    pub fn allow_origin(mut self, chain: ChainId) -> Self {
+        self.allowed_origins.get_or_insert_with(HashSet::new).insert(chain);
+        self
+    }
+
+    pub fn trust_reserve(mut self, asset_id: AssetId, chain: ChainId) -> Self {
+        self.trusted_reserves.entry(asset_id).or_default().insert(chain);
+        self
+    }
+
+    pub fn with_max_amount(mut self, amount: Balance) -> Self {
+        self.max_amount_per_message = Some(amount);
+        self
+    }
+
+    pub fn with_rate_limit(mut self, max_messages: u32, window_blocks: u64) -> Self {
+        self.rate_limit = Some(RateLimit { max_messages, window_blocks });
+        self
+    }
+}
+
+/// Transfers buffered ahead of a gap, per origin chain.
+pub const MAX_BUFFERED_MESSAGES: usize = 16;
+
+/// Replay protection for transfers arriving from one origin chain.
+#[derive(Default)]
+struct InboundChannel {
+    next_sequence: u64,
+    processed: HashSet<MessageId>,
+    buffered: BTreeMap<u64, TransferMessage>,
+}
 
 pub struct AssetPallet {
     balances: HashMap<(AccountId, AssetId), Balance>,
+    assets: HashMap<AssetId, AssetDetails>,
+    total_supply: HashMap<AssetId, Balance>,
+    trapped: HashMap<TrapId, TrappedAssets>,
+    next_trap_id: TrapId,
+    weight_price: Balance,
+    teleport_allowed: HashSet<(AssetId, ChainId)>,
+    barriers: BarrierConfig,
+    /// Messages accepted per origin chain, keyed by the start block of the current window.
+    channel_usage: HashMap<ChainId, (u64, u32)>,
+    block_number: u64,
+    next_message_id: MessageId,
+    /// Sequence number of the next outgoing transfer, per destination chain.
+    outbound_sequences: HashMap<ChainId, u64>,
+    inbound: HashMap<ChainId, InboundChannel>,
+    /// Sent transfers by id, until an `ErrorReport` about them is handled.
+    sent_transfers: HashMap<MessageId, TransferMessage>,
+    /// Sent asset programs, until the destination executes or bounces them.
+    sent_programs: Vec<XcmMessage>,
     chain_id: ChainId,
 }
 
@@ -56,6 +280,20 @@ impl AssetPallet {
     pub fn new(chain_id: ChainId) -> Self {
         Self {
             balances: HashMap::new(),
+            assets: HashMap::new(),
+            total_supply: HashMap::new(),
+            trapped: HashMap::new(),
+            next_trap_id: 0,
+            weight_price: 0,
+            teleport_allowed: HashSet::new(),
+            barriers: BarrierConfig::default(),
+            channel_usage: HashMap::new(),
+            block_number: 0,
+            next_message_id: 0,
+            outbound_sequences: HashMap::new(),
+            inbound: HashMap::new(),
+            sent_transfers: HashMap::new(),
+            sent_programs: Vec::new(),
             chain_id
         }
     }
@@ -68,7 +306,18 @@ impl AssetPallet {
         self.balances.get(&(account.clone(), *asset_id)).copied().unwrap_or(0)
     }
 
+    pub fn total_supply(&self, asset_id: &AssetId) -> Balance {
+        self.total_supply.get(asset_id).copied().unwrap_or(0)
+    }
+
+    pub fn asset_details(&self, asset_id: &AssetId) -> Option<&AssetDetails> {
+        self.assets.get(asset_id)
+    }
+
     pub fn set_balance(&mut self, account: &AccountId, asset_id: AssetId, amount: Balance) {
+        let current = self.balance_of(account, &asset_id);
+        let supply = self.total_supply(&asset_id).saturating_sub(current).saturating_add(amount);
+        self.total_supply.insert(asset_id, supply);
         if amount == 0 {
             self.balances.remove(&(account.clone(), asset_id));
         } else {
@@ -76,17 +325,84 @@ impl AssetPallet {
         }
     }
 
-    fn increase_balance(&mut self, account: &AccountId, asset_id: AssetId, amount: Balance) {
-        let current = self.balance_of(account, &asset_id);
-        self.set_balance(account, asset_id, current + amount);
+    pub fn create_asset(
+        &mut self,
+        owner: &AccountId,
+        asset_id: AssetId,
+        admin: &AccountId,
+        metadata: AssetMetadata,
+    ) -> Result<(), Error> {
+        match asset_id {
+            AssetId::MainToken => return Err(Error::InvalidAssetId),
+            AssetId::Foreign { chain, .. } if chain == self.chain_id => return Err(Error::InvalidAssetId),
+            _ => {}
+        }
+        if self.assets.contains_key(&asset_id) {return Err(Error::AssetAlreadyExists)}
+        let details = AssetDetails { owner: owner.clone(), admin: admin.clone(), metadata };
+        self.assets.insert(asset_id, details);
+        Ok(())
+    }
+
+    pub fn set_admin(&mut self, origin: &AccountId, asset_id: AssetId, new_admin: &AccountId) -> Result<(), Error> {
+        let details = self.assets.get_mut(&asset_id).ok_or(Error::UnknownAsset)?;
+        if details.owner != *origin {return Err(Error::NoPermission)}
+        details.admin = new_admin.clone();
+        Ok(())
+    }
+
+    pub fn transfer_ownership(&mut self, origin: &AccountId, asset_id: AssetId, new_owner: &AccountId) -> Result<(), Error> {
+        let details = self.assets.get_mut(&asset_id).ok_or(Error::UnknownAsset)?;
+        if details.owner != *origin {return Err(Error::NoPermission)}
+        details.owner = new_owner.clone();
+        Ok(())
+    }
+
+    pub fn mint(&mut self, origin: &AccountId, asset_id: AssetId, beneficiary: &AccountId, amount: Balance) -> Result<(), Error> {
+        self.ensure_admin(origin, &asset_id)?;
+        self.increase_balance(beneficiary, asset_id, amount)
+    }
+
+    pub fn burn(&mut self, origin: &AccountId, asset_id: AssetId, who: &AccountId, amount: Balance) -> Result<(), Error> {
+        self.ensure_admin(origin, &asset_id)?;
+        self.decrease_balance(who, asset_id, amount)
+    }
+
+    fn ensure_admin(&self, origin: &AccountId, asset_id: &AssetId) -> Result<(), Error> {
+        let details = self.assets.get(asset_id).ok_or(Error::UnknownAsset)?;
+        if details.admin != *origin {return Err(Error::NoPermission)}
+        Ok(())
+    }
+
+    fn min_balance(&self, asset_id: &AssetId) -> Result<Balance, Error> {
+        match asset_id {
+            AssetId::MainToken => Ok(0),
+            _ => self.assets.get(asset_id).map(|details| details.metadata.min_balance).ok_or(Error::UnknownAsset),
+        }
+    }
+
+    fn increase_balance(&mut self, account: &AccountId, asset_id: AssetId, amount: Balance) -> Result<(), Error> {
+        let min_balance = self.min_balance(&asset_id)?;
+        let new_balance = self.balance_of(account, &asset_id).try_add(amount)?;
+        // Only a guard: `set_balance` updates the supply, which must not overflow either.
+        self.total_supply(&asset_id).try_add(amount)?;
+        if new_balance < min_balance {
+            return Err(Error::BelowMinimumBalance);
+        }
+        self.set_balance(account, asset_id, new_balance);
+        Ok(())
     }
 
     fn decrease_balance(&mut self, account: &AccountId, asset_id: AssetId, amount: Balance) -> Result<(), Error> {
+        let min_balance = self.min_balance(&asset_id)?;
         let current = self.balance_of(account, &asset_id);
         if current < amount {
             return Err(Error::InsufficientBalance);
         }
-        self.set_balance(account, asset_id, current - amount);
+        let new_balance = current.try_sub(amount)?;
+        if new_balance != 0 && new_balance < min_balance {
+            return Err(Error::BelowMinimumBalance);
+        }
+        self.set_balance(account, asset_id, new_balance);
         Ok(())
     }
 
@@ -99,109 +415,2293 @@ impl AssetPallet {
         amount: Balance,
     ) -> Result<TransferMessage, Error> {
         if destination_chain == self.chain_id {return Err(Error::InvalidDestinationChain)};
-        if amount <= 0 {return Err(Error::ZeroAmountTransfer)};
+        if amount == 0 {return Err(Error::ZeroAmountTransfer)};
         self.decrease_balance(sender, asset_id, amount)?;
+        let id = self.next_message_id;
+        self.next_message_id += 1;
+        let sequence = self.outbound_sequences.entry(destination_chain).or_insert(0);
         let transfer_msg =TransferMessage::new(
-            self.chain_id, destination_chain, sender.clone(), beneficiary.clone(), asset_id, amount);
+            self.chain_id, destination_chain, sender.clone(), beneficiary.clone(), asset_id.to_global(self.chain_id), amount)
+            .with_sequence(id, *sequence);
+        *sequence += 1;
+        self.sent_transfers.insert(id, transfer_msg.clone());
         Ok(transfer_msg)
     }
 
+    /// Deposits a transfer that carries exactly the expected sequence number for its origin.
+    ///
+    /// Once the sequence check passes the message uses up its sequence number and id, even
+    /// if a barrier or the deposit rejects it afterwards: `handle_incoming_transfer` bounces
+    /// or traps a rejected message, so it must not be deposited by a later replay, and the
+    /// messages behind it on the channel must not wait for it.
     pub fn process_incoming_transfer(
         &mut self,
         message: TransferMessage,
     ) -> Result<(), Error> {
         if message.to_chain != self.chain_id {return Err(Error::InvalidDestinationChain)}
-        self.increase_balance(&message.to_account, message.asset_id, message.amount);
+        self.check_sequence(&message)?;
+        self.consume_sequence(&message);
+        let asset_id = message.asset_id.to_local(self.chain_id);
+        self.check_barriers(message.from_chain, &[(asset_id, message.amount)])?;
+        if let Some(reserves) = self.barriers.trusted_reserves.get(&asset_id) {
+            if !reserves.contains(&message.from_chain) {return Err(Error::UntrustedReserve)}
+        }
+        self.record_accepted_message(message.from_chain);
+        self.increase_balance(&message.to_account, asset_id, message.amount)
+    }
+
+    /// Like `process_incoming_transfer`, but turns a failure into an `ErrorReport` for the origin.
+    ///
+    /// Messages for another chain, for an asset this chain does not know, or stopped by a
+    /// barrier are bounced back for a refund. Known assets that cannot be deposited are
+    /// trapped here instead.
+    /// Returns `Ok(None)` when the transfer was deposited. Replayed and out-of-order messages
+    /// are returned as errors without a report, since bouncing them could refund twice.
+    pub fn handle_incoming_transfer(&mut self, message: TransferMessage) -> Result<Option<ErrorReport>, Error> {
+        let Err(error) = self.process_incoming_transfer(message.clone()) else { return Ok(None) };
+        let trap_id = match error {
+            Error::DuplicateMessage | Error::MessageOutOfOrder => return Err(error),
+            Error::InvalidDestinationChain
+            | Error::UnknownAsset
+            | Error::OriginNotAllowed
+            | Error::UntrustedReserve
+            | Error::AmountTooLarge
+            | Error::RateLimited => None,
+            _ => Some(self.trap_assets(
+                message.from_chain,
+                &message.from_account,
+                message.asset_id.to_local(self.chain_id),
+                message.amount,
+                Some(error.clone()),
+            )),
+        };
+        Ok(Some(ErrorReport { transfer: message, error, trap_id }))
+    }
+
+    /// Entry point for transfers arriving over a channel.
+    ///
+    /// A message ahead of the expected sequence number is buffered, up to
+    /// `MAX_BUFFERED_MESSAGES` per origin, and handled once the gap is filled, so one
+    /// call can handle several messages and return a report for each that failed.
+    pub fn receive_transfer(&mut self, message: TransferMessage) -> Result<Vec<ErrorReport>, Error> {
+        self.receive_and_track(message).map(|(_, reports)| reports)
+    }
+
+    /// `receive_transfer` that also returns the ids of the transfers it deposited, for the
+    /// router to acknowledge to their origin.
+    fn receive_and_track(&mut self, message: TransferMessage) -> Result<(Vec<MessageId>, Vec<ErrorReport>), Error> {
+        let from_chain = message.from_chain;
+        if message.to_chain == self.chain_id && message.sequence > self.expected_sequence(from_chain) {
+            return self.buffer_transfer(message).map(|_| (Vec::new(), Vec::new()));
+        }
+        let mut deposited = Vec::new();
+        let mut reports = Vec::new();
+        let id = message.id;
+        match self.handle_incoming_transfer(message)? {
+            Some(report) => reports.push(report),
+            None => deposited.push(id),
+        }
+        while let Some(channel) = self.inbound.get_mut(&from_chain) {
+            let Some(next) = channel.buffered.remove(&channel.next_sequence) else { break };
+            let id = next.id;
+            match self.handle_incoming_transfer(next) {
+                Ok(Some(report)) => reports.push(report),
+                Ok(None) => deposited.push(id),
+                // A buffered message whose id was processed in the meantime still fills its slot.
+                Err(_) => self.inbound.entry(from_chain).or_default().next_sequence += 1,
+            }
+        }
+        Ok((deposited, reports))
+    }
+
+    /// Sequence number the next transfer from `origin_chain` must carry.
+    pub fn expected_sequence(&self, origin_chain: ChainId) -> u64 {
+        self.inbound.get(&origin_chain).map_or(0, |channel| channel.next_sequence)
+    }
+
+    pub fn is_processed(&self, origin_chain: ChainId, id: MessageId) -> bool {
+        self.inbound.get(&origin_chain).is_some_and(|channel| channel.processed.contains(&id))
+    }
+
+    pub fn buffered_count(&self, origin_chain: ChainId) -> usize {
+        self.inbound.get(&origin_chain).map_or(0, |channel| channel.buffered.len())
+    }
+
+    fn check_sequence(&self, message: &TransferMessage) -> Result<(), Error> {
+        let Some(channel) = self.inbound.get(&message.from_chain) else {
+            return if message.sequence == 0 {Ok(())} else {Err(Error::MessageOutOfOrder)};
+        };
+        if channel.processed.contains(&message.id) || message.sequence < channel.next_sequence {
+            return Err(Error::DuplicateMessage);
+        }
+        if message.sequence > channel.next_sequence {return Err(Error::MessageOutOfOrder)}
+        Ok(())
+    }
+
+    /// Marks `message`'s id as processed and moves its channel to the next sequence number.
+    fn consume_sequence(&mut self, message: &TransferMessage) {
+        let channel = self.inbound.entry(message.from_chain).or_default();
+        channel.processed.insert(message.id);
+        channel.next_sequence += 1;
+    }
+
+    fn buffer_transfer(&mut self, message: TransferMessage) -> Result<(), Error> {
+        let channel = self.inbound.entry(message.from_chain).or_default();
+        let already_seen = channel.processed.contains(&message.id)
+            || channel.buffered.values().any(|buffered| buffered.id == message.id);
+        if already_seen || channel.buffered.contains_key(&message.sequence) {return Err(Error::DuplicateMessage)}
+        if channel.buffered.len() >= MAX_BUFFERED_MESSAGES {return Err(Error::MessageOutOfOrder)}
+        channel.buffered.insert(message.sequence, message);
+        Ok(())
+    }
+
+    /// Handles a report from `reporter` about a transfer this chain sent, refunding the sender
+    /// when nothing was trapped.
+    ///
+    /// The refund comes from the record kept by `initiate_transfer`, which the report must
+    /// match on id, sender, asset and amount, and only the transfer's destination may send it.
+    /// The record is dropped once handled or once `acknowledge_transfer` confirms the deposit.
+    pub fn process_error_report(&mut self, reporter: ChainId, report: ErrorReport) -> Result<(), Error> {
+        let reported = &report.transfer;
+        if reported.from_chain != self.chain_id {return Err(Error::InvalidReportDestination)}
+        let sent = self.sent_transfers.get(&reported.id).ok_or(Error::UnknownTransfer)?;
+        if sent.to_chain != reporter {return Err(Error::InvalidReportOrigin)}
+        if (&sent.from_account, sent.asset_id, sent.amount) != (&reported.from_account, reported.asset_id, reported.amount) {
+            return Err(Error::UnknownTransfer);
+        }
+        if report.trap_id.is_none() {
+            let (account, asset_id, amount) = (sent.from_account.clone(), sent.asset_id.to_local(self.chain_id), sent.amount);
+            self.increase_balance(&account, asset_id, amount)?;
+        }
+        self.sent_transfers.remove(&reported.id);
+        Ok(())
+    }
+
+    /// Forgets sent transfer `id` once `destination` confirmed depositing it, so no later
+    /// report can refund it.
+    pub fn acknowledge_transfer(&mut self, destination: ChainId, id: MessageId) {
+        if self.sent_transfers.get(&id).is_some_and(|sent| sent.to_chain == destination) {
+            self.sent_transfers.remove(&id);
+        }
+    }
+
+    fn trap_assets(
+        &mut self,
+        origin_chain: ChainId,
+        origin_account: &AccountId,
+        asset_id: AssetId,
+        amount: Balance,
+        error: Option<Error>,
+    ) -> TrapId {
+        let trap_id = self.next_trap_id;
+        self.next_trap_id += 1;
+        self.trapped.insert(trap_id, TrappedAssets {
+            origin_chain,
+            origin_account: origin_account.clone(),
+            asset_id,
+            amount,
+            error,
+        });
+        trap_id
+    }
+
+    pub fn trapped_assets(&self, trap_id: TrapId) -> Option<&TrappedAssets> {
+        self.trapped.get(&trap_id)
+    }
+
+    /// Releases trapped assets to `beneficiary` on this chain.
+    ///
+    /// Allowed for the original sender (`claimer_chain` + `claimer` matching the trap's origin)
+    /// and for the asset's admin on this chain. The trap is kept if the deposit fails again.
+    pub fn claim_trapped_assets(
+        &mut self,
+        claimer_chain: ChainId,
+        claimer: &AccountId,
+        trap_id: TrapId,
+        beneficiary: &AccountId,
+    ) -> Result<(), Error> {
+        let trap = self.trapped.get(&trap_id).ok_or(Error::UnknownTrap)?;
+        let is_sender = trap.origin_chain == claimer_chain && trap.origin_account == *claimer;
+        let is_admin = claimer_chain == self.chain_id && self.ensure_admin(claimer, &trap.asset_id).is_ok();
+        if !is_sender && !is_admin {return Err(Error::NoPermission)}
+
+        let (asset_id, amount) = (trap.asset_id, trap.amount);
+        self.increase_balance(beneficiary, asset_id, amount)?;
+        self.trapped.remove(&trap_id);
         Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::advanced::challenge_11::{AssetId, AssetPallet, ChainId, Error};
+pub type XcmWeight = u64;
 
-    #[test]
-    pub fn initiate_transfer_test() {
-        let sender = &"alice".to_string();
-        let to_chain = ChainId(2);
-        let to = &"bob".to_string();
-       let mut pallet = AssetPallet::new(ChainId(1));
-        pallet.set_balance(sender, AssetId::MainToken, 20);
-        let result =
-            pallet.initiate_transfer(sender, to_chain, to, AssetId::MainToken, 10);
-        assert!(result.is_ok());
-        let transfer_msg = result.unwrap();
-        assert_eq!(transfer_msg.from_account, sender.clone());
-        assert_eq!(transfer_msg.to_account, to.clone());
-        assert_eq!(transfer_msg.asset_id, AssetId::MainToken);
-        assert_eq!(transfer_msg.amount, 10);
+/// JSON: `{"id":"MainToken","amount":"10"}`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Asset {
+    pub id: AssetId,
+    #[serde(with = "as_string")]
+    pub amount: Balance,
+}
+
+impl Asset {
+    pub fn new(id: AssetId, amount: Balance) -> Self {
+        Self { id, amount }
     }
+}
 
-    #[test]
-    pub fn initiate_transfer_sender_balance_insufficient_fail() {
-        let sender = &"alice".to_string();
-        let to_chain = ChainId(2);
-        let to = &"bob".to_string();
-        let mut pallet = AssetPallet::new(ChainId(1));
-        let result =
-            pallet.initiate_transfer(sender, to_chain, to, AssetId::MainToken, 10);
-        assert!(result.is_err());
-        assert_eq!(result, Err(Error::InsufficientBalance));
+/// Which assets of the holding register an instruction applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssetFilter {
+    All,
+    Only(AssetId),
+}
+
+/// Who is executing an XCM program: an account on a chain.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub chain: ChainId,
+    pub account: AccountId,
+}
+
+impl Location {
+    pub fn new(chain: ChainId, account: &str) -> Self {
+        Self { chain, account: account.to_string() }
     }
+}
 
-    #[test]
-    pub fn initiate_transfer_invalid_destinataion_fail() {
-        let sender = &"alice".to_string();
-        let to = &"bob".to_string();
-        let mut pallet = AssetPallet::new(ChainId(1));
-        let result =
-            pallet.initiate_transfer(sender, ChainId(1), to, AssetId::MainToken, 10);
-        assert!(result.is_err());
-        assert_eq!(result, Err(Error::InvalidDestinationChain));
+/// Instructions understood by `AssetPallet::execute_xcm`, modelled on XCM v3.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Instruction {
+    /// Moves assets from the origin's local account into holding.
+    WithdrawAsset(Asset),
+    /// Credits holding with derivative assets whose reserve is the origin chain.
+    ReserveAssetDeposited(Asset),
+    /// Credits holding with assets burnt on the origin chain.
+    ReceiveTeleportedAsset(Asset),
+    /// Pays for `weight_limit` (or the whole program) out of `fees` taken from holding; the
+    /// limit must cover the whole program.
+    BuyExecution { fees: Asset, weight_limit: Option<XcmWeight> },
+    /// Empties the matching holding assets into `beneficiary`'s account.
+    DepositAsset { assets: AssetFilter, beneficiary: AccountId },
+    /// Drops the origin; later instructions needing one fail with `BadOrigin`.
+    ClearOrigin,
+    /// Returns fees paid for weight that was bought but not used back into holding.
+    RefundSurplus,
+}
+
+impl Instruction {
+    pub fn weight(&self) -> XcmWeight {
+        match self {
+            Instruction::WithdrawAsset(_) => 20_000,
+            Instruction::ReserveAssetDeposited(_) => 15_000,
+            Instruction::ReceiveTeleportedAsset(_) => 15_000,
+            Instruction::BuyExecution { .. } => 10_000,
+            Instruction::DepositAsset { .. } => 25_000,
+            Instruction::ClearOrigin => 1_000,
+            Instruction::RefundSurplus => 5_000,
+        }
     }
+}
 
-    #[test]
-    pub fn initiate_transfer_invalid_amount_fail() {
-        let sender = &"alice".to_string();
-        let to = &"bob".to_string();
-        let mut pallet = AssetPallet::new(ChainId(1));
-        pallet.set_balance(sender, AssetId::MainToken, 20);
-        let result =
-            pallet.initiate_transfer(sender, ChainId(2), to, AssetId::MainToken, 0);
-        assert!(result.is_err());
-        assert_eq!(result, Err(Error::ZeroAmountTransfer));
+/// An XCM program: instructions run in order against one holding register.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Xcm(pub Vec<Instruction>);
+
+impl Xcm {
+    pub fn weight(&self) -> XcmWeight {
+        self.0.iter().map(Instruction::weight).fold(0, XcmWeight::saturating_add)
     }
+}
 
-    #[test]
-    pub fn transfer_test() {
-        let sender = &"alice".to_string();
-        let from_chain = ChainId(1);
-        let to_chain = ChainId(2);
-        let to = &"bob".to_string();
-        let mut chain_a = AssetPallet::new(from_chain);
-        let mut chain_b = AssetPallet::new(to_chain);
-        chain_a.set_balance(sender, AssetId::MainToken, 20);
-        let result =
-            chain_a.initiate_transfer(sender, to_chain, to, AssetId::MainToken, 10);
-        let transfer_msg = result.unwrap();
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum XcmError {
+    /// The instruction needs an origin, but there is none or it is not local to this chain.
+    BadOrigin,
+    /// The holding register does not contain enough of the asset.
+    NotHoldingAssets,
+    /// `BuyExecution` fees do not cover the requested weight, or it buys less than the program weighs.
+    TooExpensive,
+    /// Executing up to this instruction would exceed the weight limit.
+    WeightLimitReached,
+    /// `ReserveAssetDeposited` came from a chain that is not the asset's reserve.
+    UntrustedReserveLocation,
+    /// `ReceiveTeleportedAsset` came from a chain not on the teleport allow-list for the asset.
+    UntrustedTeleportLocation,
+    /// The message was stopped by the pallet's `BarrierConfig` before execution.
+    Barrier(Error),
+    /// An asset operation on the pallet failed.
+    Asset(Error),
+}
 
-        let transfer_result = chain_b.process_incoming_transfer(transfer_msg);
-        assert!(transfer_result.is_ok());
+/// Where and why an XCM program stopped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutionError {
+    /// Index of the failing instruction in the program.
+    pub index: usize,
+    pub error: XcmError,
+    pub weight_used: XcmWeight,
+    /// Traps created for assets still in holding when execution stopped.
+    pub trap_ids: Vec<TrapId>,
+}
 
-        assert_eq!(chain_a.balance_of(sender, &AssetId::MainToken), 10);
-        assert_eq!(chain_b.balance_of(to, &AssetId::MainToken), 10);
+impl ExecutionError {
+    /// Whether the program stopped before any asset reached holding, so the sender's
+    /// chain still accounts for everything it sent.
+    pub fn assets_untouched(&self) -> bool {
+        self.trap_ids.is_empty() && (self.index == 0 || self.weight_used == 0)
     }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutionOutcome {
+    pub weight_used: XcmWeight,
+    /// Traps created for assets left in holding after the last instruction.
+    pub trap_ids: Vec<TrapId>,
+}
 
+/// Per-execution state of the XCM executor.
+struct Executor {
+    origin: Option<Location>,
+    holding: BTreeMap<AssetId, Balance>,
+    /// Weight paid for by `BuyExecution` and the asset it was paid in.
+    bought: Option<(AssetId, XcmWeight)>,
+    weight_used: XcmWeight,
+}
 
+impl Executor {
+    fn add_to_holding(&mut self, asset_id: AssetId, amount: Balance) -> Result<(), XcmError> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let held = self.holding.entry(asset_id).or_insert(0);
+        *held = (*held).try_add(amount).map_err(|error| XcmError::Asset(error.into()))?;
+        Ok(())
+    }
 
+    fn take_from_holding(&mut self, asset_id: AssetId, amount: Balance) -> Result<(), XcmError> {
+        let held = self.holding.get_mut(&asset_id).ok_or(XcmError::NotHoldingAssets)?;
+        if *held < amount {return Err(XcmError::NotHoldingAssets)}
+        *held -= amount;
+        if *held == 0 {
+            self.holding.remove(&asset_id);
+        }
+        Ok(())
+    }
+}
 
+impl AssetPallet {
+    /// Price of one unit of XCM weight, charged by `BuyExecution`. Execution is free by default.
+    pub fn set_weight_price(&mut self, price: Balance) {
+        self.weight_price = price;
+    }
 
+    /// Runs `message` on behalf of `origin` with a fresh holding register.
+    ///
+    /// The program is rejected up front if its weight exceeds `weight_limit`. Instructions
+    /// already executed are not rolled back on error; whatever is still in holding when
+    /// execution stops is put in the asset trap, claimable by `origin`.
+    pub fn execute_xcm(
+        &mut self,
+        origin: Location,
+        message: &Xcm,
+        weight_limit: XcmWeight,
+    ) -> Result<ExecutionOutcome, ExecutionError> {
+        let mut cumulative: XcmWeight = 0;
+        for (index, instruction) in message.0.iter().enumerate() {
+            cumulative = cumulative.saturating_add(instruction.weight());
+            if cumulative > weight_limit {
+                return Err(ExecutionError { index, error: XcmError::WeightLimitReached, weight_used: 0, trap_ids: Vec::new() });
+            }
+        }
 
+        let program_weight = message.weight();
+        let mut executor = Executor {
+            origin: Some(origin.clone()),
+            holding: BTreeMap::new(),
+            bought: None,
+            weight_used: 0,
+        };
+        for (index, instruction) in message.0.iter().enumerate() {
+            executor.weight_used += instruction.weight();
+            if let Err(error) = self.execute_instruction(&mut executor, instruction, program_weight) {
+                let trap_ids = self.trap_holding(&origin, executor.holding);
+                return Err(ExecutionError { index, error, weight_used: executor.weight_used, trap_ids });
+            }
+        }
+        let weight_used = executor.weight_used;
+        let trap_ids = self.trap_holding(&origin, executor.holding);
+        Ok(ExecutionOutcome { weight_used, trap_ids })
+    }
 
+    fn execute_instruction(
+        &mut self,
+        executor: &mut Executor,
+        instruction: &Instruction,
+        program_weight: XcmWeight,
+    ) -> Result<(), XcmError> {
+        match instruction {
+            Instruction::WithdrawAsset(asset) => {
+                let origin = executor.origin.as_ref().ok_or(XcmError::BadOrigin)?;
+                let account = if origin.chain == self.chain_id {
+                    origin.account.clone()
+                } else {
+                    sovereign_account(origin.chain)
+                };
+                let asset_id = asset.id.to_local(self.chain_id);
+                executor.add_to_holding(asset_id, asset.amount)?;
+                if let Err(error) = self.decrease_balance(&account, asset_id, asset.amount) {
+                    executor.take_from_holding(asset_id, asset.amount)?;
+                    return Err(XcmError::Asset(error));
+                }
+            }
+            Instruction::ReserveAssetDeposited(asset) => {
+                let origin = executor.origin.as_ref().ok_or(XcmError::BadOrigin)?;
+                if origin.chain == self.chain_id {return Err(XcmError::BadOrigin)}
+                let asset_id = asset.id.to_local(self.chain_id);
+                if !self.is_trusted_reserve(&asset_id, origin.chain) {return Err(XcmError::UntrustedReserveLocation)}
+                executor.add_to_holding(asset_id, asset.amount)?;
+            }
+            Instruction::ReceiveTeleportedAsset(asset) => {
+                let origin = executor.origin.as_ref().ok_or(XcmError::BadOrigin)?;
+                if origin.chain == self.chain_id {return Err(XcmError::BadOrigin)}
+                let asset_id = asset.id.to_local(self.chain_id);
+                if !self.is_teleport_allowed(&asset_id, origin.chain) {return Err(XcmError::UntrustedTeleportLocation)}
+                executor.add_to_holding(asset_id, asset.amount)?;
+            }
+            Instruction::BuyExecution { fees, weight_limit } => {
+                let fee_asset = fees.id.to_local(self.chain_id);
+                if executor.holding.get(&fee_asset).copied().unwrap_or(0) < fees.amount {
+                    return Err(XcmError::NotHoldingAssets);
+                }
+                let weight = weight_limit.unwrap_or(program_weight);
+                if weight < program_weight {return Err(XcmError::TooExpensive)}
+                let cost = Balance::from(weight).saturating_mul(self.weight_price);
+                if cost > fees.amount {return Err(XcmError::TooExpensive)}
+                executor.take_from_holding(fee_asset, cost)?;
+                executor.bought = Some((fee_asset, weight));
+            }
+            Instruction::DepositAsset { assets, beneficiary } => {
+                let matching: Vec<(AssetId, Balance)> = executor.holding
+                    .iter()
+                    .filter(|(asset_id, _)| match assets {
+                        AssetFilter::All => true,
+                        AssetFilter::Only(id) => id.to_local(self.chain_id) == **asset_id,
+                    })
+                    .map(|(asset_id, amount)| (*asset_id, *amount))
+                    .collect();
+                for (asset_id, amount) in matching {
+                    self.increase_balance(beneficiary, asset_id, amount).map_err(XcmError::Asset)?;
+                    executor.holding.remove(&asset_id);
+                }
+            }
+            Instruction::ClearOrigin => {
+                executor.origin = None;
+            }
+            Instruction::RefundSurplus => {
+                if let Some((fee_asset, weight)) = executor.bought {
+                    let surplus = weight.saturating_sub(program_weight);
+                    executor.add_to_holding(fee_asset, Balance::from(surplus).saturating_mul(self.weight_price))?;
+                    executor.bought = Some((fee_asset, weight - surplus));
+                }
+            }
+        }
+        Ok(())
+    }
 
+    fn trap_holding(&mut self, origin: &Location, holding: BTreeMap<AssetId, Balance>) -> Vec<TrapId> {
+        holding
+            .into_iter()
+            .map(|(asset_id, amount)| self.trap_assets(origin.chain, &origin.account, asset_id, amount, None))
+            .collect()
+    }
+}
 
+/// A program sent from one chain to another, executed with `Location { from_chain, origin_account }` as origin.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct XcmMessage {
+    pub from_chain: ChainId,
+    pub to_chain: ChainId,
+    pub origin_account: AccountId,
+    pub program: Xcm,
 }
 
+impl AssetPallet {
+    /// Trusts `chain` to burn and mint `asset_id` 1:1 with this chain, in both directions.
+    pub fn allow_teleport(&mut self, asset_id: AssetId, chain: ChainId) {
+        self.teleport_allowed.insert((asset_id, chain));
+    }
+
+    pub fn is_teleport_allowed(&self, asset_id: &AssetId, chain: ChainId) -> bool {
+        self.teleport_allowed.contains(&(*asset_id, chain))
+    }
+
+    /// Whether `chain` may deposit derivatives of `asset_id` here. The asset's own reserve is
+    /// trusted unless `BarrierConfig::trusted_reserves` lists reserves for it explicitly.
+    pub fn is_trusted_reserve(&self, asset_id: &AssetId, chain: ChainId) -> bool {
+        match self.barriers.trusted_reserves.get(asset_id) {
+            Some(reserves) => reserves.contains(&chain),
+            None => self.reserve_of(asset_id) == Some(chain),
+        }
+    }
 
+    /// Chain holding the real asset behind `asset_id`; `MainToken` has none and can only be teleported.
+    pub fn reserve_of(&self, asset_id: &AssetId) -> Option<ChainId> {
+        match asset_id {
+            AssetId::MainToken => None,
+            AssetId::Local(_) => Some(self.chain_id),
+            AssetId::Foreign { chain, .. } => Some(*chain),
+        }
+    }
 
+    /// Burns `amount` here and asks `destination_chain` to mint it for `beneficiary`.
+    ///
+    /// Both sides must list each other in their teleport allow-list for the asset.
+    pub fn teleport_assets(
+        &mut self,
+        sender: &AccountId,
+        destination_chain: ChainId,
+        beneficiary: &AccountId,
+        asset_id: AssetId,
+        amount: Balance,
+    ) -> Result<XcmMessage, Error> {
+        if destination_chain == self.chain_id {return Err(Error::InvalidDestinationChain)}
+        if amount == 0 {return Err(Error::ZeroAmountTransfer)}
+        if !self.is_teleport_allowed(&asset_id, destination_chain) {return Err(Error::UntrustedTeleport)}
+        self.decrease_balance(sender, asset_id, amount)?;
+        let asset = Asset::new(asset_id.to_global(self.chain_id), amount);
+        Ok(self.deposit_program(sender, destination_chain, beneficiary, Instruction::ReceiveTeleportedAsset(asset)))
+    }
 
+    /// Moves `amount` to `destination_chain` through the asset's reserve.
+    ///
+    /// From the reserve, the funds go into the destination's sovereign account here and
+    /// the destination mints a derivative. Towards the reserve, the derivative is burnt here
+    /// and the reserve releases the funds from this chain's sovereign account.
+    pub fn reserve_transfer_assets(
+        &mut self,
+        sender: &AccountId,
+        destination_chain: ChainId,
+        beneficiary: &AccountId,
+        asset_id: AssetId,
+        amount: Balance,
+    ) -> Result<XcmMessage, Error> {
+        if destination_chain == self.chain_id {return Err(Error::InvalidDestinationChain)}
+        if amount == 0 {return Err(Error::ZeroAmountTransfer)}
+        let asset = Asset::new(asset_id.to_global(self.chain_id), amount);
+        let first = match self.reserve_of(&asset_id) {
+            Some(reserve) if reserve == self.chain_id => {
+                self.transfer_local(sender, &sovereign_account(destination_chain), asset_id, amount)?;
+                Instruction::ReserveAssetDeposited(asset)
+            }
+            Some(reserve) if reserve == destination_chain => {
+                self.decrease_balance(sender, asset_id, amount)?;
+                Instruction::WithdrawAsset(asset)
+            }
+            _ => return Err(Error::NotReserveTransferable),
+        };
+        Ok(self.deposit_program(sender, destination_chain, beneficiary, first))
+    }
+
+    fn deposit_program(
+        &mut self,
+        sender: &AccountId,
+        destination_chain: ChainId,
+        beneficiary: &AccountId,
+        first: Instruction,
+    ) -> XcmMessage {
+        let message = XcmMessage {
+            from_chain: self.chain_id,
+            to_chain: destination_chain,
+            origin_account: sender.clone(),
+            program: Xcm(vec![
+                first,
+                Instruction::ClearOrigin,
+                Instruction::DepositAsset { assets: AssetFilter::All, beneficiary: beneficiary.clone() },
+            ]),
+        };
+        self.sent_programs.push(message.clone());
+        message
+    }
+
+    /// Undoes the source side of a program this chain sent, after `reporter`, its destination,
+    /// rejected it before taking in any asset.
+    ///
+    /// Teleported assets and burnt derivatives are minted back to the sender; reserve
+    /// deposits are released from the destination's sovereign account.
+    pub fn process_xcm_bounce(&mut self, reporter: ChainId, message: XcmMessage) -> Result<(), Error> {
+        if message.from_chain != self.chain_id {return Err(Error::InvalidReportDestination)}
+        if message.to_chain != reporter {return Err(Error::InvalidReportOrigin)}
+        let position = self.sent_programs.iter().position(|sent| *sent == message).ok_or(Error::UnknownTransfer)?;
+        let sender = &message.origin_account;
+        match message.program.0.first() {
+            Some(Instruction::ReceiveTeleportedAsset(asset)) | Some(Instruction::WithdrawAsset(asset)) => {
+                self.increase_balance(sender, asset.id.to_local(self.chain_id), asset.amount)?;
+            }
+            Some(Instruction::ReserveAssetDeposited(asset)) => {
+                self.transfer_local(&sovereign_account(reporter), sender, asset.id.to_local(self.chain_id), asset.amount)?;
+            }
+            _ => return Err(Error::UnknownTransfer),
+        }
+        self.sent_programs.remove(position);
+        Ok(())
+    }
+
+    /// Forgets a sent program once `destination` executed it, so it can no longer bounce.
+    pub fn acknowledge_xcm(&mut self, destination: ChainId, message: &XcmMessage) {
+        if message.to_chain != destination {return}
+        if let Some(position) = self.sent_programs.iter().position(|sent| sent == message) {
+            self.sent_programs.remove(position);
+        }
+    }
+
+    /// Moves `amount` between two accounts, checking both sides before writing either.
+    fn transfer_local(&mut self, from: &AccountId, to: &AccountId, asset_id: AssetId, amount: Balance) -> Result<(), Error> {
+        let min_balance = self.min_balance(&asset_id)?;
+        let from_balance = self.balance_of(from, &asset_id);
+        if from_balance < amount {
+            return Err(Error::InsufficientBalance);
+        }
+        let new_from_balance = from_balance.try_sub(amount)?;
+        if new_from_balance != 0 && new_from_balance < min_balance {
+            return Err(Error::BelowMinimumBalance);
+        }
+        if from == to {return Ok(())}
+        let new_to_balance = self.balance_of(to, &asset_id).try_add(amount)?;
+        if new_to_balance < min_balance {
+            return Err(Error::BelowMinimumBalance);
+        }
+        self.set_balance(from, asset_id, new_from_balance);
+        self.set_balance(to, asset_id, new_to_balance);
+        Ok(())
+    }
+
+    /// Executes an incoming `XcmMessage` with its sender as origin.
+    ///
+    /// A barrier rejection leaves every asset untouched; the router bounces such messages
+    /// back to the sender's chain rather than dropping them.
+    pub fn receive_xcm(&mut self, message: &XcmMessage, weight_limit: XcmWeight) -> Result<ExecutionOutcome, ExecutionError> {
+        if message.to_chain != self.chain_id {
+            let error = XcmError::Asset(Error::InvalidDestinationChain);
+            return Err(ExecutionError { index: 0, error, weight_used: 0, trap_ids: Vec::new() });
+        }
+        let assets: Vec<(AssetId, Balance)> = message.program.0
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::WithdrawAsset(asset)
+                | Instruction::ReserveAssetDeposited(asset)
+                | Instruction::ReceiveTeleportedAsset(asset) => Some((asset.id.to_local(self.chain_id), asset.amount)),
+                _ => None,
+            })
+            .collect();
+        if let Err(error) = self.check_barriers(message.from_chain, &assets) {
+            return Err(ExecutionError { index: 0, error: XcmError::Barrier(error), weight_used: 0, trap_ids: Vec::new() });
+        }
+        self.record_accepted_message(message.from_chain);
+        let origin = Location { chain: message.from_chain, account: message.origin_account.clone() };
+        self.execute_xcm(origin, &message.program, weight_limit)
+    }
+
+    fn trapped_amount(&self, asset_id: &AssetId) -> Balance {
+        self.trapped.values().filter(|trap| trap.asset_id == *asset_id).fold(0, |total, trap| total.saturating_add(trap.amount))
+    }
+}
+
+impl AssetPallet {
+    pub fn set_barrier_config(&mut self, config: BarrierConfig) {
+        self.barriers = config;
+    }
+
+    pub fn barrier_config(&self) -> &BarrierConfig {
+        &self.barriers
+    }
+
+    /// Current block, used to window the per-channel rate limit.
+    pub fn set_block_number(&mut self, block_number: u64) {
+        self.block_number = block_number;
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    /// Origin allow-list, per-message maximum and rate limit, checked in that order.
+    fn check_barriers(&self, origin_chain: ChainId, assets: &[(AssetId, Balance)]) -> Result<(), Error> {
+        if let Some(allowed) = &self.barriers.allowed_origins {
+            if !allowed.contains(&origin_chain) {return Err(Error::OriginNotAllowed)}
+        }
+        if let Some(max_amount) = self.barriers.max_amount_per_message {
+            if assets.iter().any(|(_, amount)| *amount > max_amount) {return Err(Error::AmountTooLarge)}
+        }
+        if let Some(limit) = self.barriers.rate_limit {
+            let window_start = self.window_start(limit);
+            let used = match self.channel_usage.get(&origin_chain) {
+                Some((start, count)) if *start == window_start => *count,
+                _ => 0,
+            };
+            if used >= limit.max_messages {return Err(Error::RateLimited)}
+        }
+        Ok(())
+    }
+
+    fn record_accepted_message(&mut self, origin_chain: ChainId) {
+        let Some(limit) = self.barriers.rate_limit else { return };
+        let window_start = self.window_start(limit);
+        let usage = self.channel_usage.entry(origin_chain).or_insert((window_start, 0));
+        if usage.0 != window_start {
+            *usage = (window_start, 0);
+        }
+        usage.1 += 1;
+    }
+
+    fn window_start(&self, limit: RateLimit) -> u64 {
+        let window = limit.window_blocks.max(1);
+        (self.block_number / window) * window
+    }
+}
+
+/// Version byte that prefixes every encoded `TransferMessage` and `XcmMessage`.
+pub const MESSAGE_VERSION: u8 = 1;
+
+fn check_version(input: &mut &[u8]) -> Result<(), CodecError> {
+    match read_byte(input)? {
+        MESSAGE_VERSION => Ok(()),
+        version => Err(CodecError::UnknownVersion(version)),
+    }
+}
+
+impl Encode for ChainId {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.0.encode_to(out);
+    }
+}
+
+impl Decode for ChainId {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(ChainId(u32::decode(input)?))
+    }
+}
+
+impl Encode for AssetId {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            AssetId::MainToken => out.push(0),
+            AssetId::Local(id) => {
+                out.push(1);
+                id.encode_to(out);
+            }
+            AssetId::Foreign { chain, id } => {
+                out.push(2);
+                chain.encode_to(out);
+                id.encode_to(out);
+            }
+        }
+    }
+}
+
+impl Decode for AssetId {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match read_byte(input)? {
+            0 => Ok(AssetId::MainToken),
+            1 => Ok(AssetId::Local(u32::decode(input)?)),
+            2 => Ok(AssetId::Foreign { chain: ChainId::decode(input)?, id: u32::decode(input)? }),
+            tag => Err(CodecError::InvalidTag(tag)),
+        }
+    }
+}
+
+/// Bytes: `MESSAGE_VERSION`, both chains as `u32`, id and sequence as `u64`, both accounts,
+/// the asset id and a compact amount.
+impl Encode for TransferMessage {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(MESSAGE_VERSION);
+        self.from_chain.encode_to(out);
+        self.to_chain.encode_to(out);
+        self.id.encode_to(out);
+        self.sequence.encode_to(out);
+        self.from_account.encode_to(out);
+        self.to_account.encode_to(out);
+        self.asset_id.encode_to(out);
+        encode_compact(self.amount, out);
+    }
+}
+
+impl Decode for TransferMessage {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        check_version(input)?;
+        Ok(Self {
+            from_chain: ChainId::decode(input)?,
+            to_chain: ChainId::decode(input)?,
+            id: MessageId::decode(input)?,
+            sequence: u64::decode(input)?,
+            from_account: AccountId::decode(input)?,
+            to_account: AccountId::decode(input)?,
+            asset_id: AssetId::decode(input)?,
+            amount: decode_compact(input)?,
+        })
+    }
+}
+
+impl Encode for Asset {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.id.encode_to(out);
+        encode_compact(self.amount, out);
+    }
+}
+
+impl Decode for Asset {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Asset::new(AssetId::decode(input)?, decode_compact(input)?))
+    }
+}
+
+impl Encode for AssetFilter {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            AssetFilter::All => out.push(0),
+            AssetFilter::Only(id) => {
+                out.push(1);
+                id.encode_to(out);
+            }
+        }
+    }
+}
+
+impl Decode for AssetFilter {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match read_byte(input)? {
+            0 => Ok(AssetFilter::All),
+            1 => Ok(AssetFilter::Only(AssetId::decode(input)?)),
+            tag => Err(CodecError::InvalidTag(tag)),
+        }
+    }
+}
+
+impl Encode for Instruction {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            Instruction::WithdrawAsset(asset) => {
+                out.push(0);
+                asset.encode_to(out);
+            }
+            Instruction::ReserveAssetDeposited(asset) => {
+                out.push(1);
+                asset.encode_to(out);
+            }
+            Instruction::ReceiveTeleportedAsset(asset) => {
+                out.push(2);
+                asset.encode_to(out);
+            }
+            Instruction::BuyExecution { fees, weight_limit } => {
+                out.push(3);
+                fees.encode_to(out);
+                weight_limit.encode_to(out);
+            }
+            Instruction::DepositAsset { assets, beneficiary } => {
+                out.push(4);
+                assets.encode_to(out);
+                beneficiary.encode_to(out);
+            }
+            Instruction::ClearOrigin => out.push(5),
+            Instruction::RefundSurplus => out.push(6),
+        }
+    }
+}
+
+impl Decode for Instruction {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match read_byte(input)? {
+            0 => Ok(Instruction::WithdrawAsset(Asset::decode(input)?)),
+            1 => Ok(Instruction::ReserveAssetDeposited(Asset::decode(input)?)),
+            2 => Ok(Instruction::ReceiveTeleportedAsset(Asset::decode(input)?)),
+            3 => Ok(Instruction::BuyExecution { fees: Asset::decode(input)?, weight_limit: Option::decode(input)? }),
+            4 => Ok(Instruction::DepositAsset { assets: AssetFilter::decode(input)?, beneficiary: AccountId::decode(input)? }),
+            5 => Ok(Instruction::ClearOrigin),
+            6 => Ok(Instruction::RefundSurplus),
+            tag => Err(CodecError::InvalidTag(tag)),
+        }
+    }
+}
+
+/// Bytes: `MESSAGE_VERSION`, both chains as `u32`, the origin account and the instructions as a `Vec`.
+impl Encode for XcmMessage {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(MESSAGE_VERSION);
+        self.from_chain.encode_to(out);
+        self.to_chain.encode_to(out);
+        self.origin_account.encode_to(out);
+        self.program.0.encode_to(out);
+    }
+}
+
+impl Decode for XcmMessage {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        check_version(input)?;
+        Ok(Self {
+            from_chain: ChainId::decode(input)?,
+            to_chain: ChainId::decode(input)?,
+            origin_account: AccountId::decode(input)?,
+            program: Xcm(Vec::decode(input)?),
+        })
+    }
+}
+
+/// Anything the `MessageRouter` carries over a channel.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RoutedMessage {
+    Transfer(TransferMessage),
+    ErrorReport(ErrorReport),
+    Xcm(XcmMessage),
+    /// An `XcmMessage` sent back by its destination, which rejected it before taking in any asset.
+    XcmBounce(XcmMessage),
+}
+
+impl RoutedMessage {
+    /// `(sender, receiver)` chains of this hop.
+    pub fn route(&self) -> (ChainId, ChainId) {
+        match self {
+            RoutedMessage::Transfer(message) => (message.from_chain, message.to_chain),
+            RoutedMessage::ErrorReport(report) => (report.transfer.to_chain, report.transfer.from_chain),
+            RoutedMessage::Xcm(message) => (message.from_chain, message.to_chain),
+            RoutedMessage::XcmBounce(message) => (message.to_chain, message.from_chain),
+        }
+    }
+}
+
+/// Why a routed message had no effect, or only a partial one, on its destination.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeliveryError {
+    Rejected(Error),
+    Execution(ExecutionError),
+}
+
+impl From<Error> for DeliveryError {
+    fn from(error: Error) -> Self {
+        DeliveryError::Rejected(error)
+    }
+}
+
+/// Outcome of delivering one queued message during `MessageRouter::tick`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeliveryReport {
+    pub block_number: u64,
+    pub message: RoutedMessage,
+    pub result: Result<(), DeliveryError>,
+}
+
+/// Weight budget the router gives each `XcmMessage` on delivery.
+pub const MAX_MESSAGE_WEIGHT: XcmWeight = 1_000_000;
+
+/// Hosts several `AssetPallet`s and relays `TransferMessage`s and `XcmMessage`s between them.
+///
+/// Messages are queued per `(from_chain, to_chain)` channel and only delivered
+/// on `tick`, so a transfer is observable "in flight" like on a real network.
+/// Failed transfers send an `ErrorReport` back, and programs rejected before touching
+/// their assets an `XcmBounce`, delivered on the following tick.
+pub struct MessageRouter {
+    chains: BTreeMap<ChainId, AssetPallet>,
+    channels: BTreeMap<(ChainId, ChainId), VecDeque<RoutedMessage>>,
+    block_number: u64,
+}
+
+impl MessageRouter {
+    pub fn new() -> Self {
+        Self {
+            chains: BTreeMap::new(),
+            channels: BTreeMap::new(),
+            block_number: 0,
+        }
+    }
+
+    pub fn add_chain(&mut self, pallet: AssetPallet) -> Result<(), Error> {
+        let chain_id = pallet.get_chain_id();
+        if self.chains.contains_key(&chain_id) {return Err(Error::ChainAlreadyRegistered)}
+        self.chains.insert(chain_id, pallet);
+        Ok(())
+    }
+
+    pub fn chain(&self, chain_id: ChainId) -> Option<&AssetPallet> {
+        self.chains.get(&chain_id)
+    }
+
+    pub fn chain_mut(&mut self, chain_id: ChainId) -> Option<&mut AssetPallet> {
+        self.chains.get_mut(&chain_id)
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    /// Starts a transfer on `from_chain` and queues the resulting message for delivery.
+    pub fn transfer(
+        &mut self,
+        from_chain: ChainId,
+        sender: &AccountId,
+        destination_chain: ChainId,
+        beneficiary: &AccountId,
+        asset_id: AssetId,
+        amount: Balance,
+    ) -> Result<(), Error> {
+        if !self.chains.contains_key(&destination_chain) {return Err(Error::UnknownChain)}
+        let source = self.chains.get_mut(&from_chain).ok_or(Error::UnknownChain)?;
+        let message = source.initiate_transfer(sender, destination_chain, beneficiary, asset_id, amount)?;
+        self.send(message)
+    }
+
+    /// Queues a message produced outside the router, e.g. by `AssetPallet::initiate_transfer`.
+    pub fn send(&mut self, message: TransferMessage) -> Result<(), Error> {
+        self.enqueue(RoutedMessage::Transfer(message))
+    }
+
+    /// Queues a program produced by `AssetPallet::teleport_assets` or `reserve_transfer_assets`.
+    pub fn send_xcm(&mut self, message: XcmMessage) -> Result<(), Error> {
+        self.enqueue(RoutedMessage::Xcm(message))
+    }
+
+    fn enqueue(&mut self, message: RoutedMessage) -> Result<(), Error> {
+        let (from_chain, to_chain) = message.route();
+        if !self.chains.contains_key(&from_chain) || !self.chains.contains_key(&to_chain) {
+            return Err(Error::UnknownChain);
+        }
+        self.channels.entry((from_chain, to_chain)).or_default().push_back(message);
+        Ok(())
+    }
+
+    pub fn queued(&self, from_chain: ChainId, to_chain: ChainId) -> usize {
+        self.channels.get(&(from_chain, to_chain)).map_or(0, VecDeque::len)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.channels.values().map(VecDeque::len).sum()
+    }
+
+    /// Advances one block and delivers every message queued before it, channel by channel in FIFO order.
+    pub fn tick(&mut self) -> Vec<DeliveryReport> {
+        self.block_number += 1;
+        for pallet in self.chains.values_mut() {
+            pallet.set_block_number(self.block_number);
+        }
+        let channels = std::mem::take(&mut self.channels);
+        let mut reports = Vec::new();
+        for ((from_chain, to_chain), queue) in channels {
+            for message in queue {
+                let result = self.deliver(from_chain, to_chain, message.clone());
+                reports.push(DeliveryReport { block_number: self.block_number, message, result });
+            }
+        }
+        reports
+    }
+
+    fn deliver(&mut self, from_chain: ChainId, to_chain: ChainId, message: RoutedMessage) -> Result<(), DeliveryError> {
+        let destination = self.chains.get_mut(&to_chain).ok_or(Error::UnknownChain)?;
+        match message {
+            RoutedMessage::Transfer(transfer) => {
+                let (deposited, reports) = destination.receive_and_track(transfer.clone())?;
+                if let Some(origin) = self.chains.get_mut(&from_chain) {
+                    for id in deposited {
+                        origin.acknowledge_transfer(to_chain, id);
+                    }
+                }
+                let mut result = Ok(());
+                for report in reports {
+                    if report.transfer.id == transfer.id {
+                        result = Err(report.error.clone().into());
+                    }
+                    self.enqueue(RoutedMessage::ErrorReport(report))?;
+                }
+                result
+            }
+            RoutedMessage::ErrorReport(report) => Ok(destination.process_error_report(from_chain, report)?),
+            RoutedMessage::Xcm(message) => {
+                let result = destination.receive_xcm(&message, MAX_MESSAGE_WEIGHT);
+                match &result {
+                    Err(error) if error.assets_untouched() => self.enqueue(RoutedMessage::XcmBounce(message))?,
+                    _ => {
+                        if let Some(origin) = self.chains.get_mut(&from_chain) {
+                            origin.acknowledge_xcm(to_chain, &message);
+                        }
+                    }
+                }
+                result.map(|_| ()).map_err(DeliveryError::Execution)
+            }
+            RoutedMessage::XcmBounce(message) => Ok(destination.process_xcm_bounce(from_chain, message)?),
+        }
+    }
+
+    /// Ticks until no message is in flight, returning every delivery report.
+    pub fn run_until_idle(&mut self) -> Vec<DeliveryReport> {
+        let mut reports = Vec::new();
+        while self.pending_count() > 0 {
+            reports.extend(self.tick());
+        }
+        reports
+    }
+
+    /// Units of an asset in existence across all chains, with `asset_id` in its global form.
+    ///
+    /// Sums every chain's supply and trapped amount, minus reserves held in sovereign
+    /// accounts, which already show up as derivative supply elsewhere. Assets inside
+    /// in-flight messages are not counted, so compare values taken while idle.
+    pub fn global_issuance(&self, asset_id: AssetId) -> Balance {
+        let mut issuance: Balance = 0;
+        for (chain_id, pallet) in &self.chains {
+            let local = asset_id.to_local(*chain_id);
+            issuance = issuance
+                .saturating_add(pallet.total_supply(&local))
+                .saturating_add(pallet.trapped_amount(&local));
+            for other in self.chains.keys().filter(|other| *other != chain_id) {
+                issuance = issuance.saturating_sub(pallet.balance_of(&sovereign_account(*other), &local));
+            }
+        }
+        issuance
+    }
+
+    /// Checks that each chain's derivatives of a reserve-backed asset are fully backed by its
+    /// sovereign account on the reserve chain. `MainToken` has no reserve and always passes.
+    pub fn verify_reserve_backing(&self, asset_id: AssetId) -> Result<(), Error> {
+        let reserve_chain = match asset_id {
+            AssetId::Foreign { chain, .. } => chain,
+            _ => return Ok(()),
+        };
+        let reserve = self.chains.get(&reserve_chain).ok_or(Error::UnknownChain)?;
+        for (chain_id, pallet) in self.chains.iter().filter(|(chain_id, _)| **chain_id != reserve_chain) {
+            let derivatives = pallet.total_supply(&asset_id).saturating_add(pallet.trapped_amount(&asset_id));
+            let backing = reserve.balance_of(&sovereign_account(*chain_id), &asset_id.to_local(reserve_chain));
+            if derivatives != backing {return Err(Error::SupplyMismatch)}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::advanced::challenge_11::{
+        sovereign_account, Asset, AssetFilter, AssetId, AssetMetadata, AssetPallet, Balance, BarrierConfig, ChainId,
+        DeliveryError, Error, ErrorReport, ExecutionError, MAX_BUFFERED_MESSAGES, Instruction, Location, MessageRouter, RoutedMessage, TransferMessage, Xcm,
+        XcmError, XcmMessage, MESSAGE_VERSION,
+    };
+    use crate::common::codec::{CodecError, Decode, Encode};
+
+    fn create_router(chain_count: u32) -> MessageRouter {
+        let mut router = MessageRouter::new();
+        for id in 1..=chain_count {
+            let mut pallet = AssetPallet::new(ChainId(id));
+            pallet.set_balance(&"alice".to_string(), AssetId::MainToken, 100);
+            router.add_chain(pallet).unwrap();
+        }
+        router
+    }
+
+    fn create_registered_asset(pallet: &mut AssetPallet, asset_id: AssetId) {
+        let owner = &"owner".to_string();
+        let admin = &"admin".to_string();
+        let metadata = AssetMetadata::new("USDT", 6, 10);
+        assert_eq!(pallet.create_asset(owner, asset_id, admin, metadata), Ok(()));
+    }
+
+    #[test]
+    pub fn initiate_transfer_test() {
+        let sender = &"alice".to_string();
+        let to_chain = ChainId(2);
+        let to = &"bob".to_string();
+       let mut pallet = AssetPallet::new(ChainId(1));
+        pallet.set_balance(sender, AssetId::MainToken, 20);
+        let result =
+            pallet.initiate_transfer(sender, to_chain, to, AssetId::MainToken, 10);
+        assert!(result.is_ok());
+        let transfer_msg = result.unwrap();
+        assert_eq!(transfer_msg.from_account, sender.clone());
+        assert_eq!(transfer_msg.to_account, to.clone());
+        assert_eq!(transfer_msg.asset_id, AssetId::MainToken);
+        assert_eq!(transfer_msg.amount, 10);
+    }
+
+    #[test]
+    pub fn initiate_transfer_sender_balance_insufficient_fail() {
+        let sender = &"alice".to_string();
+        let to_chain = ChainId(2);
+        let to = &"bob".to_string();
+        let mut pallet = AssetPallet::new(ChainId(1));
+        let result =
+            pallet.initiate_transfer(sender, to_chain, to, AssetId::MainToken, 10);
+        assert!(result.is_err());
+        assert_eq!(result, Err(Error::InsufficientBalance));
+    }
+
+    #[test]
+    pub fn initiate_transfer_invalid_destinataion_fail() {
+        let sender = &"alice".to_string();
+        let to = &"bob".to_string();
+        let mut pallet = AssetPallet::new(ChainId(1));
+        let result =
+            pallet.initiate_transfer(sender, ChainId(1), to, AssetId::MainToken, 10);
+        assert!(result.is_err());
+        assert_eq!(result, Err(Error::InvalidDestinationChain));
+    }
+
+    #[test]
+    pub fn initiate_transfer_invalid_amount_fail() {
+        let sender = &"alice".to_string();
+        let to = &"bob".to_string();
+        let mut pallet = AssetPallet::new(ChainId(1));
+        pallet.set_balance(sender, AssetId::MainToken, 20);
+        let result =
+            pallet.initiate_transfer(sender, ChainId(2), to, AssetId::MainToken, 0);
+        assert!(result.is_err());
+        assert_eq!(result, Err(Error::ZeroAmountTransfer));
+    }
+
+    #[test]
+    pub fn transfer_test() {
+        let sender = &"alice".to_string();
+        let from_chain = ChainId(1);
+        let to_chain = ChainId(2);
+        let to = &"bob".to_string();
+        let mut chain_a = AssetPallet::new(from_chain);
+        let mut chain_b = AssetPallet::new(to_chain);
+        chain_a.set_balance(sender, AssetId::MainToken, 20);
+        let result =
+            chain_a.initiate_transfer(sender, to_chain, to, AssetId::MainToken, 10);
+        let transfer_msg = result.unwrap();
+
+        let transfer_result = chain_b.process_incoming_transfer(transfer_msg);
+        assert!(transfer_result.is_ok());
+
+        assert_eq!(chain_a.balance_of(sender, &AssetId::MainToken), 10);
+        assert_eq!(chain_b.balance_of(to, &AssetId::MainToken), 10);
+    }
+
+    #[test]
+    pub fn transfer_message_json_round_trip() {
+        let message = TransferMessage::new(
+            ChainId(1), ChainId(2), "alice".to_string(), "bob".to_string(), AssetId::MainToken, Balance::MAX)
+            .with_sequence(7, 3);
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(json, format!(
+            "{{\"from_chain\":1,\"to_chain\":2,\"id\":7,\"sequence\":3,\"from_account\":\"alice\",\"to_account\":\"bob\",\"asset_id\":\"MainToken\",\"amount\":\"{}\"}}",
+            Balance::MAX));
+        let decoded: TransferMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[test]
+    pub fn transfer_message_from_json_fixture() {
+        let json = r#"{"from_chain":1,"to_chain":2,"id":0,"sequence":0,"from_account":"alice","to_account":"bob","asset_id":"MainToken","amount":"10"}"#;
+        let message: TransferMessage = serde_json::from_str(json).unwrap();
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        assert!(chain_b.process_incoming_transfer(message).is_ok());
+        assert_eq!(chain_b.balance_of(&"bob".to_string(), &AssetId::MainToken), 10);
+
+        let numeric_amount = json.replace("\"10\"", "10");
+        assert!(serde_json::from_str::<TransferMessage>(&numeric_amount).is_err());
+    }
+
+    #[test]
+    pub fn create_asset_test() {
+        let mut pallet = AssetPallet::new(ChainId(1));
+        create_registered_asset(&mut pallet, AssetId::Local(1));
+        let details = pallet.asset_details(&AssetId::Local(1)).unwrap();
+        assert_eq!(details.owner, "owner".to_string());
+        assert_eq!(details.admin, "admin".to_string());
+        assert_eq!(details.metadata, AssetMetadata::new("USDT", 6, 10));
+
+        let owner = &"owner".to_string();
+        let metadata = AssetMetadata::new("USDT", 6, 10);
+        assert_eq!(pallet.create_asset(owner, AssetId::Local(1), owner, metadata.clone()), Err(Error::AssetAlreadyExists));
+        assert_eq!(pallet.create_asset(owner, AssetId::MainToken, owner, metadata.clone()), Err(Error::InvalidAssetId));
+        assert_eq!(
+            pallet.create_asset(owner, AssetId::Foreign { chain: ChainId(1), id: 2 }, owner, metadata),
+            Err(Error::InvalidAssetId)
+        );
+    }
+
+    #[test]
+    pub fn mint_and_burn_track_total_supply() {
+        let admin = &"admin".to_string();
+        let alice = &"alice".to_string();
+        let asset = AssetId::Local(1);
+        let mut pallet = AssetPallet::new(ChainId(1));
+        create_registered_asset(&mut pallet, asset);
+
+        assert_eq!(pallet.mint(admin, asset, alice, 100), Ok(()));
+        assert_eq!(pallet.balance_of(alice, &asset), 100);
+        assert_eq!(pallet.total_supply(&asset), 100);
+
+        assert_eq!(pallet.burn(admin, asset, alice, 40), Ok(()));
+        assert_eq!(pallet.balance_of(alice, &asset), 60);
+        assert_eq!(pallet.total_supply(&asset), 60);
+
+        assert_eq!(pallet.burn(admin, asset, alice, 60), Ok(()));
+        assert_eq!(pallet.total_supply(&asset), 0);
+        assert_eq!(pallet.burn(admin, asset, alice, 1), Err(Error::InsufficientBalance));
+    }
+
+    #[test]
+    pub fn mint_and_burn_respect_min_balance() {
+        let admin = &"admin".to_string();
+        let alice = &"alice".to_string();
+        let asset = AssetId::Local(1);
+        let mut pallet = AssetPallet::new(ChainId(1));
+        create_registered_asset(&mut pallet, asset);
+
+        assert_eq!(pallet.mint(admin, asset, alice, 9), Err(Error::BelowMinimumBalance));
+        assert_eq!(pallet.mint(admin, asset, alice, 15), Ok(()));
+        assert_eq!(pallet.burn(admin, asset, alice, 6), Err(Error::BelowMinimumBalance));
+        assert_eq!(pallet.balance_of(alice, &asset), 15);
+        assert_eq!(pallet.total_supply(&asset), 15);
+    }
+
+    #[test]
+    pub fn asset_permissions_test() {
+        let owner = &"owner".to_string();
+        let admin = &"admin".to_string();
+        let mallory = &"mallory".to_string();
+        let asset = AssetId::Local(1);
+        let mut pallet = AssetPallet::new(ChainId(1));
+        create_registered_asset(&mut pallet, asset);
+
+        assert_eq!(pallet.mint(owner, asset, owner, 100), Err(Error::NoPermission));
+        assert_eq!(pallet.mint(admin, AssetId::Local(2), admin, 100), Err(Error::UnknownAsset));
+        assert_eq!(pallet.set_admin(mallory, asset, mallory), Err(Error::NoPermission));
+
+        assert_eq!(pallet.set_admin(owner, asset, mallory), Ok(()));
+        assert_eq!(pallet.mint(admin, asset, admin, 100), Err(Error::NoPermission));
+        assert_eq!(pallet.mint(mallory, asset, mallory, 100), Ok(()));
+
+        assert_eq!(pallet.transfer_ownership(owner, asset, mallory), Ok(()));
+        assert_eq!(pallet.set_admin(owner, asset, owner), Err(Error::NoPermission));
+        assert_eq!(pallet.asset_details(&asset).unwrap().owner, mallory.clone());
+    }
+
+    #[test]
+    pub fn transfer_registered_asset_between_chains() {
+        let admin = &"admin".to_string();
+        let alice = &"alice".to_string();
+        let bob = &"bob".to_string();
+        let mut chain_a = AssetPallet::new(ChainId(1));
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        create_registered_asset(&mut chain_a, AssetId::Local(7));
+        create_registered_asset(&mut chain_b, foreign);
+        chain_a.mint(admin, AssetId::Local(7), alice, 100).unwrap();
+
+        let message = chain_a.initiate_transfer(alice, ChainId(2), bob, AssetId::Local(7), 30).unwrap();
+        assert_eq!(message.asset_id, foreign);
+        assert_eq!(chain_b.process_incoming_transfer(message), Ok(()));
+        assert_eq!(chain_a.total_supply(&AssetId::Local(7)), 70);
+        assert_eq!(chain_b.balance_of(bob, &foreign), 30);
+        assert_eq!(chain_b.total_supply(&foreign), 30);
+
+        let message = chain_b.initiate_transfer(bob, ChainId(1), alice, foreign, 30).unwrap();
+        assert_eq!(message.asset_id, foreign);
+        assert_eq!(chain_a.process_incoming_transfer(message), Ok(()));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::Local(7)), 100);
+    }
+
+    #[test]
+    pub fn process_incoming_transfer_unknown_asset_fail() {
+        let mut chain_a = AssetPallet::new(ChainId(1));
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        let alice = &"alice".to_string();
+        create_registered_asset(&mut chain_a, AssetId::Local(7));
+        chain_a.mint(&"admin".to_string(), AssetId::Local(7), alice, 100).unwrap();
+        let message = chain_a.initiate_transfer(alice, ChainId(2), alice, AssetId::Local(7), 50).unwrap();
+        assert_eq!(chain_b.process_incoming_transfer(message), Err(Error::UnknownAsset));
+        assert_eq!(chain_a.initiate_transfer(alice, ChainId(2), alice, AssetId::Local(8), 1), Err(Error::UnknownAsset));
+    }
+
+    #[test]
+    pub fn router_delivers_on_tick() {
+        let alice = &"alice".to_string();
+        let bob = &"bob".to_string();
+        let mut router = create_router(2);
+
+        assert_eq!(router.transfer(ChainId(1), alice, ChainId(2), bob, AssetId::MainToken, 30), Ok(()));
+        assert_eq!(router.queued(ChainId(1), ChainId(2)), 1);
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::MainToken), 70);
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(bob, &AssetId::MainToken), 0);
+
+        let reports = router.tick();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].block_number, 1);
+        assert_eq!(reports[0].result, Ok(()));
+        assert_eq!(router.pending_count(), 0);
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(bob, &AssetId::MainToken), 30);
+    }
+
+    #[test]
+    pub fn router_multi_hop_transfer() {
+        let alice = &"alice".to_string();
+        let bob = &"bob".to_string();
+        let charlie = &"charlie".to_string();
+        let mut router = create_router(3);
+
+        router.transfer(ChainId(1), alice, ChainId(2), bob, AssetId::MainToken, 50).unwrap();
+        assert_eq!(router.transfer(ChainId(2), bob, ChainId(3), charlie, AssetId::MainToken, 50), Err(Error::InsufficientBalance));
+        router.tick();
+
+        router.transfer(ChainId(2), bob, ChainId(3), charlie, AssetId::MainToken, 50).unwrap();
+        let reports = router.tick();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].block_number, 2);
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(bob, &AssetId::MainToken), 0);
+        assert_eq!(router.chain(ChainId(3)).unwrap().balance_of(charlie, &AssetId::MainToken), 50);
+    }
+
+    #[test]
+    pub fn router_concurrent_transfers_in_one_block() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(3);
+
+        router.transfer(ChainId(1), alice, ChainId(2), alice, AssetId::MainToken, 10).unwrap();
+        router.transfer(ChainId(1), alice, ChainId(2), alice, AssetId::MainToken, 20).unwrap();
+        router.transfer(ChainId(3), alice, ChainId(2), alice, AssetId::MainToken, 30).unwrap();
+        router.transfer(ChainId(2), alice, ChainId(1), alice, AssetId::MainToken, 100).unwrap();
+        assert_eq!(router.queued(ChainId(1), ChainId(2)), 2);
+        assert_eq!(router.pending_count(), 4);
+
+        let reports = router.run_until_idle();
+        assert_eq!(reports.len(), 4);
+        assert!(reports.iter().all(|report| report.block_number == 1 && report.result.is_ok()));
+        let amounts: Vec<Balance> = reports.iter().map(|report| match &report.message {
+            RoutedMessage::Transfer(message) => message.amount,
+            other => panic!("expected only transfers, got {:?}", other),
+        }).collect();
+        assert_eq!(amounts, vec![10, 20, 100, 30]);
+
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::MainToken), 170);
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(alice, &AssetId::MainToken), 60);
+        assert_eq!(router.chain(ChainId(3)).unwrap().balance_of(alice, &AssetId::MainToken), 70);
+    }
+
+    #[test]
+    pub fn router_refunds_sender_when_asset_unknown_on_destination() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(2);
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        create_registered_asset(chain_a, AssetId::Local(7));
+        chain_a.mint(&"admin".to_string(), AssetId::Local(7), alice, 100).unwrap();
+
+        router.transfer(ChainId(1), alice, ChainId(2), alice, AssetId::Local(7), 50).unwrap();
+        let reports = router.tick();
+        assert_eq!(reports[0].result, Err(DeliveryError::Rejected(Error::UnknownAsset)));
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::Local(7)), 50);
+        assert_eq!(router.queued(ChainId(2), ChainId(1)), 1);
+
+        let reports = router.tick();
+        assert_eq!(reports.len(), 1);
+        match &reports[0].message {
+            RoutedMessage::ErrorReport(report) => {
+                assert_eq!(report.error, Error::UnknownAsset);
+                assert_eq!(report.trap_id, None);
+            }
+            other => panic!("expected an error report, got {:?}", other),
+        }
+        assert_eq!(reports[0].result, Ok(()));
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::Local(7)), 100);
+        assert_eq!(router.chain(ChainId(1)).unwrap().total_supply(&AssetId::Local(7)), 100);
+    }
+
+    #[test]
+    pub fn handle_incoming_transfer_wrong_chain_is_bounced() {
+        let alice = &"alice".to_string();
+        let mut chain_a = AssetPallet::new(ChainId(1));
+        let mut chain_c = AssetPallet::new(ChainId(3));
+        chain_a.set_balance(alice, AssetId::MainToken, 100);
+        let message = chain_a.initiate_transfer(alice, ChainId(2), alice, AssetId::MainToken, 40).unwrap();
+
+        let report = chain_c.handle_incoming_transfer(message).unwrap().unwrap();
+        assert_eq!(report.error, Error::InvalidDestinationChain);
+        assert_eq!(report.trap_id, None);
+        assert_eq!(chain_c.process_error_report(ChainId(3), report.clone()), Err(Error::InvalidReportDestination));
+        // Only the intended destination can vouch that the funds did not arrive
+        assert_eq!(chain_a.process_error_report(ChainId(3), report.clone()), Err(Error::InvalidReportOrigin));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::MainToken), 60);
+        assert_eq!(chain_a.process_error_report(ChainId(2), report), Ok(()));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::MainToken), 100);
+    }
+
+    #[test]
+    pub fn undepositable_assets_are_trapped_and_claimable() {
+        let alice = &"alice".to_string();
+        let bob = &"bob".to_string();
+        let mallory = &"mallory".to_string();
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut router = create_router(2);
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        create_registered_asset(chain_a, AssetId::Local(7));
+        chain_a.mint(&"admin".to_string(), AssetId::Local(7), alice, 100).unwrap();
+        create_registered_asset(router.chain_mut(ChainId(2)).unwrap(), foreign);
+
+        // 5 is below the destination's min_balance of 10.
+        router.transfer(ChainId(1), alice, ChainId(2), bob, AssetId::Local(7), 5).unwrap();
+        let reports = router.run_until_idle();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].result, Err(DeliveryError::Rejected(Error::BelowMinimumBalance)));
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::Local(7)), 95);
+
+        let chain_b = router.chain_mut(ChainId(2)).unwrap();
+        let trap = chain_b.trapped_assets(0).unwrap();
+        assert_eq!(trap.origin_chain, ChainId(1));
+        assert_eq!(trap.origin_account, alice.clone());
+        assert_eq!(trap.asset_id, foreign);
+        assert_eq!(trap.amount, 5);
+
+        assert_eq!(chain_b.claim_trapped_assets(ChainId(2), mallory, 0, mallory), Err(Error::NoPermission));
+        assert_eq!(chain_b.claim_trapped_assets(ChainId(2), alice, 0, alice), Err(Error::NoPermission));
+        assert_eq!(chain_b.claim_trapped_assets(ChainId(1), alice, 0, alice), Err(Error::BelowMinimumBalance));
+        assert!(chain_b.trapped_assets(0).is_some());
+
+        chain_b.mint(&"admin".to_string(), foreign, bob, 10).unwrap();
+        assert_eq!(chain_b.claim_trapped_assets(ChainId(1), alice, 0, bob), Ok(()));
+        assert_eq!(chain_b.balance_of(bob, &foreign), 15);
+        assert_eq!(chain_b.trapped_assets(0), None);
+        assert_eq!(chain_b.claim_trapped_assets(ChainId(1), alice, 0, bob), Err(Error::UnknownTrap));
+    }
+
+    #[test]
+    pub fn asset_admin_can_claim_trapped_assets() {
+        let admin = &"admin".to_string();
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        create_registered_asset(&mut chain_b, foreign);
+        let message = TransferMessage::new(ChainId(1), ChainId(2), "alice".to_string(), "bob".to_string(), foreign, 5);
+
+        let report = chain_b.handle_incoming_transfer(message).unwrap().unwrap();
+        assert_eq!(report.trap_id, Some(0));
+        assert_eq!(chain_b.claim_trapped_assets(ChainId(1), admin, 0, admin), Err(Error::NoPermission));
+        chain_b.mint(admin, foreign, admin, 10).unwrap();
+        assert_eq!(chain_b.claim_trapped_assets(ChainId(2), admin, 0, admin), Ok(()));
+        assert_eq!(chain_b.balance_of(admin, &foreign), 15);
+    }
+
+    #[test]
+    pub fn router_unknown_chain_fail() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(2);
+        assert_eq!(router.add_chain(AssetPallet::new(ChainId(1))), Err(Error::ChainAlreadyRegistered));
+        assert_eq!(router.transfer(ChainId(1), alice, ChainId(9), alice, AssetId::MainToken, 10), Err(Error::UnknownChain));
+        assert_eq!(router.transfer(ChainId(9), alice, ChainId(1), alice, AssetId::MainToken, 10), Err(Error::UnknownChain));
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::MainToken), 100);
+
+        let message = TransferMessage::new(ChainId(1), ChainId(9), alice.clone(), alice.clone(), AssetId::MainToken, 1);
+        assert_eq!(router.send(message), Err(Error::UnknownChain));
+        assert_eq!(router.pending_count(), 0);
+    }
+
+    #[test]
+    pub fn execute_xcm_local_withdraw_and_deposit() {
+        let alice = &"alice".to_string();
+        let bob = &"bob".to_string();
+        let mut pallet = AssetPallet::new(ChainId(1));
+        pallet.set_balance(alice, AssetId::MainToken, 100);
+        let program = Xcm(vec![
+            Instruction::WithdrawAsset(Asset::new(AssetId::MainToken, 60)),
+            Instruction::DepositAsset { assets: AssetFilter::All, beneficiary: bob.clone() },
+        ]);
+
+        let outcome = pallet.execute_xcm(Location::new(ChainId(1), "alice"), &program, 100_000).unwrap();
+        assert_eq!(outcome.weight_used, 45_000);
+        assert_eq!(outcome.weight_used, program.weight());
+        assert!(outcome.trap_ids.is_empty());
+        assert_eq!(pallet.balance_of(alice, &AssetId::MainToken), 40);
+        assert_eq!(pallet.balance_of(bob, &AssetId::MainToken), 60);
+        assert_eq!(pallet.total_supply(&AssetId::MainToken), 100);
+    }
+
+    #[test]
+    pub fn execute_xcm_remote_deposit_buys_execution_and_refunds_surplus() {
+        let bob = &"bob".to_string();
+        let mut pallet = AssetPallet::new(ChainId(2));
+        pallet.set_weight_price(1);
+        pallet.allow_teleport(AssetId::MainToken, ChainId(1));
+        let program = Xcm(vec![
+            Instruction::ReceiveTeleportedAsset(Asset::new(AssetId::MainToken, 200_000)),
+            Instruction::ClearOrigin,
+            Instruction::BuyExecution { fees: Asset::new(AssetId::MainToken, 150_000), weight_limit: Some(100_000) },
+            Instruction::RefundSurplus,
+            Instruction::DepositAsset { assets: AssetFilter::Only(AssetId::MainToken), beneficiary: bob.clone() },
+        ]);
+        assert_eq!(program.weight(), 56_000);
+
+        let outcome = pallet.execute_xcm(Location::new(ChainId(1), "alice"), &program, 100_000).unwrap();
+        assert_eq!(outcome.weight_used, 56_000);
+        // 100_000 weight bought, 44_000 of it refunded.
+        assert_eq!(pallet.balance_of(bob, &AssetId::MainToken), 144_000);
+    }
+
+    #[test]
+    pub fn buy_execution_must_cover_the_program_weight() {
+        let mut pallet = AssetPallet::new(ChainId(2));
+        pallet.set_weight_price(1);
+        pallet.allow_teleport(AssetId::MainToken, ChainId(1));
+        let program = Xcm(vec![
+            Instruction::ReceiveTeleportedAsset(Asset::new(AssetId::MainToken, 200_000)),
+            Instruction::BuyExecution { fees: Asset::new(AssetId::MainToken, 150_000), weight_limit: Some(40_000) },
+            Instruction::DepositAsset { assets: AssetFilter::All, beneficiary: "bob".to_string() },
+        ]);
+        assert_eq!(program.weight(), 50_000);
+
+        let error = pallet.execute_xcm(Location::new(ChainId(1), "alice"), &program, 100_000).unwrap_err();
+        assert_eq!((error.index, error.error), (1, XcmError::TooExpensive));
+        assert_eq!(pallet.trapped_assets(error.trap_ids[0]).unwrap().amount, 200_000);
+    }
+
+    #[test]
+    pub fn execute_xcm_rejects_program_over_weight_limit() {
+        let mut pallet = AssetPallet::new(ChainId(1));
+        pallet.set_balance(&"alice".to_string(), AssetId::MainToken, 100);
+        let program = Xcm(vec![
+            Instruction::WithdrawAsset(Asset::new(AssetId::MainToken, 60)),
+            Instruction::ClearOrigin,
+            Instruction::DepositAsset { assets: AssetFilter::All, beneficiary: "bob".to_string() },
+        ]);
+
+        let result = pallet.execute_xcm(Location::new(ChainId(1), "alice"), &program, 30_000);
+        assert_eq!(result, Err(ExecutionError { index: 2, error: XcmError::WeightLimitReached, weight_used: 0, trap_ids: vec![] }));
+        assert_eq!(pallet.balance_of(&"alice".to_string(), &AssetId::MainToken), 100);
+    }
+
+    #[test]
+    pub fn execute_xcm_reports_failing_instruction() {
+        let mut pallet = AssetPallet::new(ChainId(1));
+        pallet.set_balance(&"alice".to_string(), AssetId::MainToken, 100);
+        let origin = Location::new(ChainId(1), "alice");
+
+        let program = Xcm(vec![Instruction::ClearOrigin, Instruction::WithdrawAsset(Asset::new(AssetId::MainToken, 10))]);
+        let error = pallet.execute_xcm(origin.clone(), &program, 100_000).unwrap_err();
+        assert_eq!((error.index, error.error, error.weight_used), (1, XcmError::BadOrigin, 21_000));
+
+        let program = Xcm(vec![Instruction::ReceiveTeleportedAsset(Asset::new(AssetId::MainToken, 10))]);
+        let error = pallet.execute_xcm(origin.clone(), &program, 100_000).unwrap_err();
+        assert_eq!((error.index, error.error), (0, XcmError::BadOrigin));
+
+        let program = Xcm(vec![Instruction::WithdrawAsset(Asset::new(AssetId::MainToken, 1_000))]);
+        let error = pallet.execute_xcm(origin, &program, 100_000).unwrap_err();
+        assert_eq!((error.index, error.error), (0, XcmError::Asset(Error::InsufficientBalance)));
+    }
+
+    #[test]
+    pub fn execute_xcm_traps_holding_on_error() {
+        let alice = &"alice".to_string();
+        let mut pallet = AssetPallet::new(ChainId(1));
+        pallet.set_weight_price(1);
+        pallet.set_balance(alice, AssetId::MainToken, 100);
+        let origin = Location::new(ChainId(1), "alice");
+        let program = Xcm(vec![
+            Instruction::WithdrawAsset(Asset::new(AssetId::MainToken, 100)),
+            Instruction::BuyExecution { fees: Asset::new(AssetId::MainToken, 100), weight_limit: None },
+            Instruction::DepositAsset { assets: AssetFilter::All, beneficiary: alice.clone() },
+        ]);
+
+        let error = pallet.execute_xcm(origin, &program, 100_000).unwrap_err();
+        assert_eq!((error.index, error.error), (1, XcmError::TooExpensive));
+        assert_eq!(error.trap_ids, vec![0]);
+        assert_eq!(pallet.balance_of(alice, &AssetId::MainToken), 0);
+
+        let trap = pallet.trapped_assets(0).unwrap();
+        assert_eq!((trap.asset_id, trap.amount, trap.error.clone()), (AssetId::MainToken, 100, None));
+        assert_eq!(pallet.claim_trapped_assets(ChainId(1), alice, 0, alice), Ok(()));
+        assert_eq!(pallet.balance_of(alice, &AssetId::MainToken), 100);
+    }
+
+    #[test]
+    pub fn execute_xcm_traps_leftover_holding() {
+        let mut pallet = AssetPallet::new(ChainId(2));
+        pallet.allow_teleport(AssetId::MainToken, ChainId(1));
+        let program = Xcm(vec![Instruction::ReceiveTeleportedAsset(Asset::new(AssetId::MainToken, 10))]);
+        let outcome = pallet.execute_xcm(Location::new(ChainId(1), "alice"), &program, 100_000).unwrap();
+        assert_eq!(outcome.trap_ids, vec![0]);
+        assert_eq!(pallet.trapped_assets(0).unwrap().origin_chain, ChainId(1));
+    }
+
+    fn create_reserve_network() -> MessageRouter {
+        let mut router = create_router(3);
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        create_registered_asset(chain_a, AssetId::Local(7));
+        chain_a.mint(&"admin".to_string(), AssetId::Local(7), &"alice".to_string(), 100).unwrap();
+        create_registered_asset(router.chain_mut(ChainId(2)).unwrap(), foreign);
+        create_registered_asset(router.chain_mut(ChainId(3)).unwrap(), foreign);
+        router
+    }
+
+    #[test]
+    pub fn teleport_burns_and_mints_between_trusted_chains() {
+        let alice = &"alice".to_string();
+        let bob = &"bob".to_string();
+        let mut router = create_router(2);
+        router.chain_mut(ChainId(1)).unwrap().allow_teleport(AssetId::MainToken, ChainId(2));
+        router.chain_mut(ChainId(2)).unwrap().allow_teleport(AssetId::MainToken, ChainId(1));
+        assert_eq!(router.global_issuance(AssetId::MainToken), 200);
+
+        let message = router.chain_mut(ChainId(1)).unwrap()
+            .teleport_assets(alice, ChainId(2), bob, AssetId::MainToken, 30).unwrap();
+        assert_eq!(message.program.0[0], Instruction::ReceiveTeleportedAsset(Asset::new(AssetId::MainToken, 30)));
+        router.send_xcm(message).unwrap();
+        let reports = router.run_until_idle();
+        assert_eq!(reports[0].result, Ok(()));
+
+        assert_eq!(router.chain(ChainId(1)).unwrap().total_supply(&AssetId::MainToken), 70);
+        assert_eq!(router.chain(ChainId(2)).unwrap().total_supply(&AssetId::MainToken), 130);
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(bob, &AssetId::MainToken), 30);
+        assert_eq!(router.global_issuance(AssetId::MainToken), 200);
+    }
+
+    #[test]
+    pub fn teleport_requires_allow_list_on_both_sides() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(2);
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        assert_eq!(chain_a.teleport_assets(alice, ChainId(2), alice, AssetId::MainToken, 30), Err(Error::UntrustedTeleport));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::MainToken), 100);
+
+        chain_a.allow_teleport(AssetId::MainToken, ChainId(2));
+        let message = chain_a.teleport_assets(alice, ChainId(2), alice, AssetId::MainToken, 30).unwrap();
+        router.send_xcm(message).unwrap();
+        let reports = router.tick();
+        match &reports[0].result {
+            Err(DeliveryError::Execution(error)) => {
+                assert_eq!((error.index, error.error.clone()), (0, XcmError::UntrustedTeleportLocation));
+            }
+            other => panic!("expected an execution error, got {:?}", other),
+        }
+        // The bounce is still in flight; once delivered, the burn on the source is undone
+        router.run_until_idle();
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::MainToken), 100);
+        assert_eq!(router.global_issuance(AssetId::MainToken), 200);
+    }
+
+    #[test]
+    pub fn untrusted_reserve_deposit_is_released_from_sovereign_account() {
+        let alice = &"alice".to_string();
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut router = create_reserve_network();
+        router.chain_mut(ChainId(2)).unwrap()
+            .set_barrier_config(BarrierConfig::default().trust_reserve(foreign, ChainId(3)));
+        let message = router.chain_mut(ChainId(1)).unwrap()
+            .reserve_transfer_assets(alice, ChainId(2), alice, AssetId::Local(7), 40).unwrap();
+        router.send_xcm(message.clone()).unwrap();
+        let reports = router.run_until_idle();
+        match &reports[0].result {
+            Err(DeliveryError::Execution(error)) => assert_eq!(error.error, XcmError::UntrustedReserveLocation),
+            other => panic!("expected an execution error, got {:?}", other),
+        }
+        assert_eq!(reports[1].result, Ok(()));
+
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        assert_eq!(chain_a.balance_of(alice, &AssetId::Local(7)), 100);
+        assert_eq!(chain_a.balance_of(&sovereign_account(ChainId(2)), &AssetId::Local(7)), 0);
+        assert_eq!(chain_a.process_xcm_bounce(ChainId(2), message), Err(Error::UnknownTransfer));
+        assert_eq!(router.global_issuance(foreign), 100);
+        assert_eq!(router.verify_reserve_backing(foreign), Ok(()));
+    }
+
+    #[test]
+    pub fn reserve_transfer_moves_funds_into_sovereign_account() {
+        let alice = &"alice".to_string();
+        let bob = &"bob".to_string();
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut router = create_reserve_network();
+        assert_eq!(router.global_issuance(foreign), 100);
+
+        let message = router.chain_mut(ChainId(1)).unwrap()
+            .reserve_transfer_assets(alice, ChainId(2), bob, AssetId::Local(7), 40).unwrap();
+        router.send_xcm(message).unwrap();
+        assert!(router.run_until_idle().iter().all(|report| report.result.is_ok()));
+
+        let chain_a = router.chain(ChainId(1)).unwrap();
+        assert_eq!(chain_a.balance_of(alice, &AssetId::Local(7)), 60);
+        assert_eq!(chain_a.balance_of(&sovereign_account(ChainId(2)), &AssetId::Local(7)), 40);
+        assert_eq!(chain_a.total_supply(&AssetId::Local(7)), 100);
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(bob, &foreign), 40);
+        assert_eq!(router.global_issuance(foreign), 100);
+        assert_eq!(router.verify_reserve_backing(foreign), Ok(()));
+
+        let message = router.chain_mut(ChainId(2)).unwrap()
+            .reserve_transfer_assets(bob, ChainId(1), alice, foreign, 15).unwrap();
+        assert_eq!(message.program.0[0], Instruction::WithdrawAsset(Asset::new(foreign, 15)));
+        router.send_xcm(message).unwrap();
+        assert!(router.run_until_idle().iter().all(|report| report.result.is_ok()));
+
+        let chain_a = router.chain(ChainId(1)).unwrap();
+        assert_eq!(chain_a.balance_of(alice, &AssetId::Local(7)), 75);
+        assert_eq!(chain_a.balance_of(&sovereign_account(ChainId(2)), &AssetId::Local(7)), 25);
+        assert_eq!(router.chain(ChainId(2)).unwrap().total_supply(&foreign), 25);
+        assert_eq!(router.global_issuance(foreign), 100);
+        assert_eq!(router.verify_reserve_backing(foreign), Ok(()));
+    }
+
+    #[test]
+    pub fn reserve_transfer_requires_reserve_on_either_side() {
+        let alice = &"alice".to_string();
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut router = create_reserve_network();
+        let message = router.chain_mut(ChainId(1)).unwrap()
+            .reserve_transfer_assets(alice, ChainId(2), alice, AssetId::Local(7), 40).unwrap();
+        router.send_xcm(message).unwrap();
+        router.run_until_idle();
+
+        let chain_b = router.chain_mut(ChainId(2)).unwrap();
+        assert_eq!(chain_b.reserve_transfer_assets(alice, ChainId(3), alice, foreign, 10), Err(Error::NotReserveTransferable));
+        assert_eq!(
+            chain_b.reserve_transfer_assets(alice, ChainId(1), alice, AssetId::MainToken, 10),
+            Err(Error::NotReserveTransferable)
+        );
+        assert_eq!(chain_b.balance_of(alice, &foreign), 40);
+    }
+
+    #[test]
+    pub fn reserve_deposit_from_non_reserve_is_rejected() {
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        create_registered_asset(&mut chain_b, foreign);
+        let program = Xcm(vec![
+            Instruction::ReserveAssetDeposited(Asset::new(foreign, 10)),
+            Instruction::DepositAsset { assets: AssetFilter::All, beneficiary: "mallory".to_string() },
+        ]);
+        let error = chain_b.execute_xcm(Location::new(ChainId(3), "mallory"), &program, 100_000).unwrap_err();
+        assert_eq!((error.index, error.error), (0, XcmError::UntrustedReserveLocation));
+        assert_eq!(chain_b.total_supply(&foreign), 0);
+    }
+
+    #[test]
+    pub fn verify_reserve_backing_detects_unbacked_derivatives() {
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut router = create_reserve_network();
+        assert_eq!(router.verify_reserve_backing(foreign), Ok(()));
+        router.chain_mut(ChainId(3)).unwrap().mint(&"admin".to_string(), foreign, &"mallory".to_string(), 50).unwrap();
+        assert_eq!(router.verify_reserve_backing(foreign), Err(Error::SupplyMismatch));
+        assert_eq!(router.global_issuance(foreign), 150);
+    }
+
+    fn incoming(from_chain: u32, sequence: u64, asset_id: AssetId, amount: Balance) -> TransferMessage {
+        TransferMessage::new(ChainId(from_chain), ChainId(2), "alice".to_string(), "bob".to_string(), asset_id, amount)
+            .with_sequence(sequence, sequence)
+    }
+
+    #[test]
+    pub fn barrier_rejects_origin_not_allowed() {
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        chain_b.set_barrier_config(BarrierConfig::default().allow_origin(ChainId(1)));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(3, 0, AssetId::MainToken, 10)), Err(Error::OriginNotAllowed));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 0, AssetId::MainToken, 10)), Ok(()));
+    }
+
+    #[test]
+    pub fn barrier_rejects_untrusted_reserve() {
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        create_registered_asset(&mut chain_b, foreign);
+        chain_b.set_barrier_config(BarrierConfig::default().trust_reserve(foreign, ChainId(1)));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(3, 0, foreign, 10)), Err(Error::UntrustedReserve));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(3, 1, AssetId::MainToken, 10)), Ok(()));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 0, foreign, 10)), Ok(()));
+    }
+
+    #[test]
+    pub fn barrier_rejects_amount_too_large() {
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        chain_b.set_barrier_config(BarrierConfig::default().with_max_amount(100));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 0, AssetId::MainToken, 101)), Err(Error::AmountTooLarge));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 1, AssetId::MainToken, 100)), Ok(()));
+    }
+
+    #[test]
+    pub fn barrier_rate_limits_each_channel() {
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        chain_b.set_barrier_config(BarrierConfig::default().with_rate_limit(2, 10));
+        chain_b.set_block_number(3);
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 0, AssetId::MainToken, 1)), Ok(()));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 1, AssetId::MainToken, 1)), Ok(()));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 2, AssetId::MainToken, 1)), Err(Error::RateLimited));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(3, 0, AssetId::MainToken, 1)), Ok(()));
+
+        chain_b.set_block_number(9);
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 3, AssetId::MainToken, 1)), Err(Error::RateLimited));
+        chain_b.set_block_number(10);
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 4, AssetId::MainToken, 1)), Ok(()));
+        assert_eq!(chain_b.balance_of(&"bob".to_string(), &AssetId::MainToken), 4);
+    }
+
+    #[test]
+    pub fn barrier_rejections_are_refunded_through_router() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(2);
+        router.chain_mut(ChainId(2)).unwrap().set_barrier_config(BarrierConfig::default().with_rate_limit(1, 5));
+        router.transfer(ChainId(1), alice, ChainId(2), alice, AssetId::MainToken, 10).unwrap();
+        router.transfer(ChainId(1), alice, ChainId(2), alice, AssetId::MainToken, 20).unwrap();
+
+        let reports = router.tick();
+        assert_eq!(reports[0].result, Ok(()));
+        assert_eq!(reports[1].result, Err(DeliveryError::Rejected(Error::RateLimited)));
+        router.run_until_idle();
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::MainToken), 90);
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(alice, &AssetId::MainToken), 110);
+    }
+
+    #[test]
+    pub fn barrier_applies_to_xcm_messages() {
+        let mut router = create_reserve_network();
+        router.chain_mut(ChainId(2)).unwrap()
+            .set_barrier_config(BarrierConfig::default().allow_origin(ChainId(1)).with_max_amount(50));
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        let too_large = chain_a.reserve_transfer_assets(&"alice".to_string(), ChainId(2), &"bob".to_string(), AssetId::Local(7), 60).unwrap();
+        let chain_b = router.chain_mut(ChainId(2)).unwrap();
+        let error = chain_b.receive_xcm(&too_large, 1_000_000).unwrap_err();
+        assert!(error.assets_untouched());
+        assert_eq!((error.index, error.error), (0, XcmError::Barrier(Error::AmountTooLarge)));
+
+        let mut forged = too_large.clone();
+        forged.from_chain = ChainId(3);
+        let error = chain_b.receive_xcm(&forged, 1_000_000).unwrap_err();
+        assert_eq!(error.error, XcmError::Barrier(Error::OriginNotAllowed));
+
+        // Through the router, the rejected program bounces and the reserve releases the funds
+        router.send_xcm(too_large).unwrap();
+        let reports = router.run_until_idle();
+        assert!(matches!(&reports[0].result, Err(DeliveryError::Execution(error)) if error.error == XcmError::Barrier(Error::AmountTooLarge)));
+        assert_eq!(reports[1].result, Ok(()));
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let chain_a = router.chain(ChainId(1)).unwrap();
+        assert_eq!(chain_a.balance_of(&"alice".to_string(), &AssetId::Local(7)), 100);
+        assert_eq!(chain_a.balance_of(&sovereign_account(ChainId(2)), &AssetId::Local(7)), 0);
+        assert_eq!(router.global_issuance(foreign), 100);
+        assert_eq!(router.verify_reserve_backing(foreign), Ok(()));
+    }
+
+    #[test]
+    pub fn barrier_rejected_teleport_keeps_issuance() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(2);
+        router.chain_mut(ChainId(1)).unwrap().allow_teleport(AssetId::MainToken, ChainId(2));
+        let chain_b = router.chain_mut(ChainId(2)).unwrap();
+        chain_b.allow_teleport(AssetId::MainToken, ChainId(1));
+        chain_b.set_barrier_config(BarrierConfig::default().with_max_amount(10));
+
+        let message = router.chain_mut(ChainId(1)).unwrap()
+            .teleport_assets(alice, ChainId(2), alice, AssetId::MainToken, 30).unwrap();
+        router.send_xcm(message).unwrap();
+        let reports = router.run_until_idle();
+        assert!(matches!(&reports[0].result, Err(DeliveryError::Execution(error)) if error.error == XcmError::Barrier(Error::AmountTooLarge)));
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::MainToken), 100);
+        assert_eq!(router.global_issuance(AssetId::MainToken), 200);
+    }
+
+    #[test]
+    pub fn trusted_reserves_override_default_reserve_for_xcm() {
+        let foreign = AssetId::Foreign { chain: ChainId(1), id: 7 };
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        create_registered_asset(&mut chain_b, foreign);
+        chain_b.set_barrier_config(BarrierConfig::default().trust_reserve(foreign, ChainId(3)));
+        let program = Xcm(vec![
+            Instruction::ReserveAssetDeposited(Asset::new(foreign, 10)),
+            Instruction::DepositAsset { assets: AssetFilter::All, beneficiary: "bob".to_string() },
+        ]);
+
+        let error = chain_b.execute_xcm(Location::new(ChainId(1), "alice"), &program, 100_000).unwrap_err();
+        assert_eq!(error.error, XcmError::UntrustedReserveLocation);
+        assert!(chain_b.execute_xcm(Location::new(ChainId(3), "alice"), &program, 100_000).is_ok());
+        assert_eq!(chain_b.balance_of(&"bob".to_string(), &foreign), 10);
+    }
+
+    #[test]
+    pub fn transfer_message_binary_layout() {
+        let message = TransferMessage::new(
+            ChainId(1), ChainId(2), "al".to_string(), "bo".to_string(), AssetId::Foreign { chain: ChainId(1), id: 7 }, 100,
+        )
+        .with_sequence(3, 4);
+        let bytes = message.encode();
+        assert_eq!(
+            bytes,
+            vec![
+                MESSAGE_VERSION,
+                1, 0, 0, 0,
+                2, 0, 0, 0,
+                3, 0, 0, 0, 0, 0, 0, 0,
+                4, 0, 0, 0, 0, 0, 0, 0,
+                0x08, b'a', b'l',
+                0x08, b'b', b'o',
+                2, 1, 0, 0, 0, 7, 0, 0, 0,
+                0x91, 0x01,
+            ]
+        );
+        assert_eq!(TransferMessage::decode_all(&bytes), Ok(message));
+    }
+
+    #[test]
+    pub fn transfer_message_round_trips_max_amount() {
+        let message = TransferMessage::new(
+            ChainId(u32::MAX), ChainId(0), "alice".to_string(), String::new(), AssetId::Local(5), Balance::MAX,
+        );
+        let bytes = message.encode();
+        assert_eq!(bytes.len(), 1 + 4 + 4 + 8 + 8 + 6 + 1 + 5 + 17);
+        assert_eq!(TransferMessage::decode_all(&bytes), Ok(message));
+    }
+
+    #[test]
+    pub fn transfer_message_decode_is_strict() {
+        let bytes = TransferMessage::new(ChainId(1), ChainId(2), "alice".to_string(), "bob".to_string(), AssetId::MainToken, 10).encode();
+
+        let mut unknown_version = bytes.clone();
+        unknown_version[0] = 2;
+        assert_eq!(TransferMessage::decode_all(&unknown_version), Err(CodecError::UnknownVersion(2)));
+        assert_eq!(TransferMessage::decode_all(&[]), Err(CodecError::UnexpectedEnd));
+        for len in 1..bytes.len() {
+            assert_eq!(TransferMessage::decode_all(&bytes[..len]), Err(CodecError::UnexpectedEnd));
+        }
+        let trailing = [bytes.clone(), vec![0, 0]].concat();
+        assert_eq!(TransferMessage::decode_all(&trailing), Err(CodecError::TrailingBytes(2)));
+
+        let mut bad_asset = bytes.clone();
+        bad_asset[bytes.len() - 2] = 3;
+        assert_eq!(TransferMessage::decode_all(&bad_asset), Err(CodecError::InvalidTag(3)));
+    }
+
+    #[test]
+    pub fn xcm_message_binary_round_trip() {
+        let message = XcmMessage {
+            from_chain: ChainId(1),
+            to_chain: ChainId(2),
+            origin_account: "alice".to_string(),
+            program: Xcm(vec![
+                Instruction::WithdrawAsset(Asset::new(AssetId::MainToken, 50)),
+                Instruction::ReserveAssetDeposited(Asset::new(AssetId::Local(1), 1)),
+                Instruction::ReceiveTeleportedAsset(Asset::new(AssetId::Foreign { chain: ChainId(3), id: 4 }, 1 << 40)),
+                Instruction::BuyExecution { fees: Asset::new(AssetId::MainToken, 5), weight_limit: Some(60_000) },
+                Instruction::BuyExecution { fees: Asset::new(AssetId::MainToken, 5), weight_limit: None },
+                Instruction::ClearOrigin,
+                Instruction::RefundSurplus,
+                Instruction::DepositAsset { assets: AssetFilter::Only(AssetId::MainToken), beneficiary: "bob".to_string() },
+                Instruction::DepositAsset { assets: AssetFilter::All, beneficiary: "bob".to_string() },
+            ]),
+        };
+        let bytes = message.encode();
+        assert_eq!(&bytes[..15], &[MESSAGE_VERSION, 1, 0, 0, 0, 2, 0, 0, 0, 0x14, b'a', b'l', b'i', b'c', b'e']);
+        assert_eq!(bytes[15], 9 << 2);
+        assert_eq!(XcmMessage::decode_all(&bytes), Ok(message));
+
+        assert_eq!(XcmMessage::decode_all(&[0]), Err(CodecError::UnknownVersion(0)));
+        assert_eq!(XcmMessage::decode_all(&bytes[..bytes.len() - 1]), Err(CodecError::UnexpectedEnd));
+        let mut bad_instruction = bytes.clone();
+        bad_instruction[16] = 7;
+        assert_eq!(XcmMessage::decode_all(&bad_instruction), Err(CodecError::InvalidTag(7)));
+    }
+
+    #[test]
+    pub fn initiate_transfer_assigns_ids_and_channel_sequences() {
+        let alice = &"alice".to_string();
+        let mut chain_a = AssetPallet::new(ChainId(1));
+        chain_a.set_balance(alice, AssetId::MainToken, 100);
+        let first = chain_a.initiate_transfer(alice, ChainId(2), alice, AssetId::MainToken, 1).unwrap();
+        let second = chain_a.initiate_transfer(alice, ChainId(3), alice, AssetId::MainToken, 1).unwrap();
+        let third = chain_a.initiate_transfer(alice, ChainId(2), alice, AssetId::MainToken, 1).unwrap();
+        assert_eq!((first.id, first.sequence), (0, 0));
+        assert_eq!((second.id, second.sequence), (1, 0));
+        assert_eq!((third.id, third.sequence), (2, 1));
+    }
+
+    #[test]
+    pub fn replayed_transfer_is_rejected() {
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 0, AssetId::MainToken, 10)), Ok(()));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 0, AssetId::MainToken, 10)), Err(Error::DuplicateMessage));
+        let reused_id = incoming(1, 1, AssetId::MainToken, 10).with_sequence(0, 1);
+        assert_eq!(chain_b.process_incoming_transfer(reused_id), Err(Error::DuplicateMessage));
+        assert_eq!(chain_b.handle_incoming_transfer(incoming(1, 0, AssetId::MainToken, 10)), Err(Error::DuplicateMessage));
+
+        assert!(chain_b.is_processed(ChainId(1), 0));
+        assert_eq!(chain_b.expected_sequence(ChainId(1)), 1);
+        assert_eq!(chain_b.balance_of(&"bob".to_string(), &AssetId::MainToken), 10);
+    }
+
+    #[test]
+    pub fn out_of_order_transfers_are_buffered_until_the_gap_is_filled() {
+        let bob = &"bob".to_string();
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        assert_eq!(chain_b.process_incoming_transfer(incoming(1, 1, AssetId::MainToken, 1)), Err(Error::MessageOutOfOrder));
+
+        assert_eq!(chain_b.receive_transfer(incoming(1, 2, AssetId::MainToken, 20)), Ok(vec![]));
+        assert_eq!(chain_b.receive_transfer(incoming(1, 1, AssetId::MainToken, 10)), Ok(vec![]));
+        assert_eq!(chain_b.receive_transfer(incoming(1, 2, AssetId::MainToken, 20)), Err(Error::DuplicateMessage));
+        assert_eq!(chain_b.buffered_count(ChainId(1)), 2);
+        assert_eq!(chain_b.balance_of(bob, &AssetId::MainToken), 0);
+
+        assert_eq!(chain_b.receive_transfer(incoming(1, 0, AssetId::MainToken, 5)), Ok(vec![]));
+        assert_eq!(chain_b.buffered_count(ChainId(1)), 0);
+        assert_eq!(chain_b.expected_sequence(ChainId(1)), 3);
+        assert_eq!(chain_b.balance_of(bob, &AssetId::MainToken), 35);
+        assert_eq!(chain_b.receive_transfer(incoming(1, 1, AssetId::MainToken, 10)), Err(Error::DuplicateMessage));
+    }
+
+    #[test]
+    pub fn buffered_transfer_failures_are_reported() {
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        create_registered_asset(&mut chain_b, AssetId::Local(1));
+        assert_eq!(chain_b.receive_transfer(incoming(1, 1, AssetId::Foreign { chain: ChainId(2), id: 1 }, 5)), Ok(vec![]));
+
+        let reports = chain_b.receive_transfer(incoming(1, 0, AssetId::MainToken, 5)).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!((reports[0].transfer.sequence, &reports[0].error), (1, &Error::BelowMinimumBalance));
+        assert_eq!(reports[0].trap_id, Some(0));
+    }
+
+    #[test]
+    pub fn rejected_transfers_use_up_their_sequence_number() {
+        let bob = &"bob".to_string();
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        chain_b.set_barrier_config(BarrierConfig::default().with_max_amount(100));
+        assert_eq!(chain_b.receive_transfer(incoming(1, 1, AssetId::MainToken, 10)), Ok(vec![]));
+        assert_eq!(chain_b.receive_transfer(incoming(1, 2, AssetId::MainToken, 20)), Ok(vec![]));
+
+        // The rejected message is bounced for a refund, and the buffered ones behind it still drain
+        let reports = chain_b.receive_transfer(incoming(1, 0, AssetId::MainToken, 101)).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!((reports[0].transfer.sequence, &reports[0].error), (0, &Error::AmountTooLarge));
+        assert!(chain_b.is_processed(ChainId(1), 0));
+        assert_eq!(chain_b.expected_sequence(ChainId(1)), 3);
+        assert_eq!(chain_b.buffered_count(ChainId(1)), 0);
+        assert_eq!(chain_b.balance_of(bob, &AssetId::MainToken), 30);
+
+        // Once refunded at the origin, a replay must not be deposited here
+        chain_b.set_barrier_config(BarrierConfig::default());
+        assert_eq!(chain_b.receive_transfer(incoming(1, 0, AssetId::MainToken, 101)), Err(Error::DuplicateMessage));
+        assert_eq!(chain_b.balance_of(bob, &AssetId::MainToken), 30);
+    }
+
+    #[test]
+    pub fn buffer_is_bounded_per_origin() {
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        for sequence in 1..=MAX_BUFFERED_MESSAGES as u64 {
+            assert_eq!(chain_b.receive_transfer(incoming(1, sequence, AssetId::MainToken, 1)), Ok(vec![]));
+        }
+        let overflow = incoming(1, MAX_BUFFERED_MESSAGES as u64 + 1, AssetId::MainToken, 1);
+        assert_eq!(chain_b.receive_transfer(overflow), Err(Error::MessageOutOfOrder));
+        assert_eq!(chain_b.receive_transfer(incoming(3, 1, AssetId::MainToken, 1)), Ok(vec![]));
+    }
+
+    #[test]
+    pub fn replayed_messages_through_router_do_not_double_funds() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(2);
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        let message = chain_a.initiate_transfer(alice, ChainId(2), alice, AssetId::MainToken, 10).unwrap();
+        router.send(message.clone()).unwrap();
+        router.send(message).unwrap();
+
+        let reports = router.run_until_idle();
+        assert_eq!(reports[0].result, Ok(()));
+        assert_eq!(reports[1].result, Err(DeliveryError::Rejected(Error::DuplicateMessage)));
+        assert_eq!(router.chain(ChainId(1)).unwrap().balance_of(alice, &AssetId::MainToken), 90);
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(alice, &AssetId::MainToken), 110);
+    }
+
+    #[test]
+    pub fn replayed_error_report_does_not_refund_twice() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(2);
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        let message = chain_a.initiate_transfer(alice, ChainId(2), alice, AssetId::MainToken, 10).unwrap();
+        let mut misrouted = message.clone();
+        misrouted.to_chain = ChainId(3);
+
+        let report = router.chain_mut(ChainId(2)).unwrap().handle_incoming_transfer(misrouted).unwrap().unwrap();
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        assert_eq!(chain_a.process_error_report(ChainId(2), report.clone()), Ok(()));
+        assert_eq!(chain_a.process_error_report(ChainId(2), report), Err(Error::UnknownTransfer));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::MainToken), 100);
+    }
+
+    #[test]
+    pub fn error_report_for_unsent_transfer_is_rejected() {
+        let alice = &"alice".to_string();
+        let mut chain_a = AssetPallet::new(ChainId(1));
+        chain_a.set_balance(alice, AssetId::MainToken, 100);
+        let made_up = TransferMessage::new(ChainId(1), ChainId(2), alice.clone(), alice.clone(), AssetId::MainToken, 1_000)
+            .with_sequence(7, 0);
+        let report = ErrorReport { transfer: made_up, error: Error::UnknownAsset, trap_id: None };
+        assert_eq!(chain_a.process_error_report(ChainId(2), report), Err(Error::UnknownTransfer));
+
+        // A report about a real transfer must not inflate its amount either
+        let sent = chain_a.initiate_transfer(alice, ChainId(2), alice, AssetId::MainToken, 10).unwrap();
+        let mut inflated = sent.clone();
+        inflated.amount = 1_000;
+        let report = ErrorReport { transfer: inflated, error: Error::UnknownAsset, trap_id: None };
+        assert_eq!(chain_a.process_error_report(ChainId(2), report), Err(Error::UnknownTransfer));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::MainToken), 90);
+
+        let report = ErrorReport { transfer: sent, error: Error::UnknownAsset, trap_id: None };
+        assert_eq!(chain_a.process_error_report(ChainId(2), report), Ok(()));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::MainToken), 100);
+    }
+
+    #[test]
+    pub fn error_report_after_successful_deposit_is_rejected() {
+        let alice = &"alice".to_string();
+        let mut router = create_router(2);
+        let message = router.chain_mut(ChainId(1)).unwrap()
+            .initiate_transfer(alice, ChainId(2), alice, AssetId::MainToken, 40).unwrap();
+        router.send(message.clone()).unwrap();
+        router.run_until_idle();
+        assert_eq!(router.chain(ChainId(2)).unwrap().balance_of(alice, &AssetId::MainToken), 140);
+
+        let report = ErrorReport { transfer: message, error: Error::UnknownAsset, trap_id: None };
+        let chain_a = router.chain_mut(ChainId(1)).unwrap();
+        assert_eq!(chain_a.process_error_report(ChainId(2), report), Err(Error::UnknownTransfer));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::MainToken), 60);
+        assert_eq!(router.global_issuance(AssetId::MainToken), 200);
+    }
+
+    #[test]
+    pub fn mint_at_balance_max_overflows() {
+        let (admin, bob) = (&"admin".to_string(), &"bob".to_string());
+        let mut chain_a = AssetPallet::new(ChainId(1));
+        create_registered_asset(&mut chain_a, AssetId::Local(1));
+        assert_eq!(chain_a.mint(admin, AssetId::Local(1), bob, Balance::MAX), Ok(()));
+        assert_eq!(chain_a.mint(admin, AssetId::Local(1), bob, 1), Err(Error::Overflow));
+        assert_eq!(chain_a.mint(admin, AssetId::Local(1), admin, 10), Err(Error::Overflow));
+        assert_eq!(chain_a.balance_of(bob, &AssetId::Local(1)), Balance::MAX);
+        assert_eq!(chain_a.total_supply(&AssetId::Local(1)), Balance::MAX);
+    }
+
+    #[test]
+    pub fn reserve_transfer_checks_both_balances_before_writing() {
+        let (admin, alice) = (&"admin".to_string(), &"alice".to_string());
+        let mut chain_a = AssetPallet::new(ChainId(1));
+        create_registered_asset(&mut chain_a, AssetId::Local(7));
+        chain_a.mint(admin, AssetId::Local(7), alice, Balance::MAX).unwrap();
+
+        // Moving funds keeps the total supply, so a supply at the maximum does not block it
+        assert!(chain_a.reserve_transfer_assets(alice, ChainId(2), alice, AssetId::Local(7), 50).is_ok());
+        assert_eq!(chain_a.balance_of(&sovereign_account(ChainId(2)), &AssetId::Local(7)), 50);
+        assert_eq!(chain_a.total_supply(&AssetId::Local(7)), Balance::MAX);
+
+        let mut chain_a = AssetPallet::new(ChainId(1));
+        create_registered_asset(&mut chain_a, AssetId::Local(7));
+        chain_a.set_balance(&sovereign_account(ChainId(2)), AssetId::Local(7), Balance::MAX - 5);
+        chain_a.set_balance(alice, AssetId::Local(7), 100);
+        let result = chain_a.reserve_transfer_assets(alice, ChainId(2), alice, AssetId::Local(7), 10);
+        assert_eq!(result, Err(Error::Overflow));
+        assert_eq!(chain_a.balance_of(alice, &AssetId::Local(7)), 100);
+        assert_eq!(chain_a.balance_of(&sovereign_account(ChainId(2)), &AssetId::Local(7)), Balance::MAX - 5);
+    }
+
+    #[test]
+    pub fn overflowing_incoming_transfer_is_trapped() {
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        chain_b.set_balance(&"bob".to_string(), AssetId::MainToken, Balance::MAX);
+        let report = chain_b.handle_incoming_transfer(incoming(1, 0, AssetId::MainToken, 1)).unwrap().unwrap();
+        assert_eq!((report.error, report.trap_id), (Error::Overflow, Some(0)));
+        assert_eq!(chain_b.balance_of(&"bob".to_string(), &AssetId::MainToken), Balance::MAX);
+    }
+
+    #[test]
+    pub fn overflowing_holding_register_fails() {
+        let mut chain_b = AssetPallet::new(ChainId(2));
+        chain_b.allow_teleport(AssetId::MainToken, ChainId(1));
+        let program = Xcm(vec![
+            Instruction::ReceiveTeleportedAsset(Asset::new(AssetId::MainToken, Balance::MAX)),
+            Instruction::ReceiveTeleportedAsset(Asset::new(AssetId::MainToken, 1)),
+        ]);
+        let error = chain_b.execute_xcm(Location::new(ChainId(1), "alice"), &program, 100_000).unwrap_err();
+        assert_eq!((error.index, error.error), (1, XcmError::Asset(Error::Overflow)));
+    }
+}
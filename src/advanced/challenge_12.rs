@@ -4,9 +4,12 @@ pub type BlockNumber = u64;
 pub type Hash = [u8; 32];
 pub type Balance = u128;
 
+use serde::{Deserialize, Serialize};
+
 pub mod system {
     
     use std::collections::HashMap;
+    use serde::{Deserialize, Serialize};
 
     pub trait Config {
         type AccountId: Clone + Eq + std::hash::Hash + core::fmt::Debug;
@@ -14,7 +17,12 @@ pub mod system {
         type Hash: Clone + PartialEq + core::fmt::Debug;
     }
 
-    #[derive(Clone, Debug, PartialEq)]
+    /// JSON: `{"NewAccount":{"account":"alice"}}`
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(bound(
+        serialize = "T::AccountId: Serialize",
+        deserialize = "T::AccountId: Deserialize<'de>"
+    ))]
     pub enum Event<T: Config> {
         NewAccount { account: T::AccountId },
         ExtrinsicSuccess { account: T::AccountId },
@@ -72,31 +80,52 @@ pub mod system {
 pub mod balances {
     use super::*;
     use std::collections::HashMap;
+    use crate::common::arithmetic::{ArithmeticError, BalanceArithmetic};
+    use crate::common::serde_helpers::as_string;
 
     pub trait Config: system::Config {
-        type Balance: Clone + Copy + Default + PartialEq + PartialOrd + core::fmt::Debug +
-        core::ops::Add<Output = Self::Balance> +
-        core::ops::Sub<Output = Self::Balance>;
+        type Balance: Clone + Copy + Default + PartialEq + PartialOrd + core::fmt::Debug + BalanceArithmetic;
     }
 
     // Event system: emitted to off-chain consumers for state change notifications
-    #[derive(Clone, Debug, PartialEq)]
+    /// JSON: `{"Transfer":{"from":"alice","to":"bob","amount":"250"}}`, balances as strings.
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(bound(
+        serialize = "T::AccountId: Serialize, T::Balance: std::fmt::Display",
+        deserialize = "T::AccountId: Deserialize<'de>, T::Balance: std::str::FromStr, \
+                       <T::Balance as std::str::FromStr>::Err: std::fmt::Display"
+    ))]
     pub enum Event<T: Config> {
         Transfer {
             from: T::AccountId,
             to: T::AccountId,
+            #[serde(with = "as_string")]
             amount: T::Balance
         },
         BalanceSet {
             account: T::AccountId,
+            #[serde(with = "as_string")]
             balance: T::Balance
         },
     }
 
-    #[derive(Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
     pub enum Error {
         InsufficientBalance,
         ZeroAmount,
+        /// The recipient's balance would exceed `T::Balance`'s maximum.
+        Overflow,
+        /// The sender's balance would drop below zero.
+        Underflow,
+    }
+
+    impl From<ArithmeticError> for Error {
+        fn from(error: ArithmeticError) -> Self {
+            match error {
+                ArithmeticError::Overflow => Error::Overflow,
+                ArithmeticError::Underflow => Error::Underflow,
+            }
+        }
     }
 
     pub struct Pallet<T: Config> {
@@ -136,10 +165,12 @@ pub mod balances {
             }
 
             let to_balance = self.balances.get(&to).copied().unwrap_or_default();
+            let new_from_balance = from_balance.try_sub(amount)?;
+            let new_to_balance = to_balance.try_add(amount)?;
 
             // Update balances
-            self.balances.insert(from.clone(), from_balance - amount);
-            self.balances.insert(to.clone(), to_balance + amount);
+            self.balances.insert(from.clone(), new_from_balance);
+            self.balances.insert(to.clone(), new_to_balance);
 
             self.events.push(Event::Transfer { from, to, amount });
             Ok(())
@@ -155,7 +186,12 @@ pub mod balances {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// JSON: tagged by pallet, e.g. `{"Balances":{"Transfer":{…}}}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "system::Event<T>: Serialize, balances::Event<T>: Serialize",
+    deserialize = "system::Event<T>: Deserialize<'de>, balances::Event<T>: Deserialize<'de>"
+))]
 pub enum RuntimeEvent<T: RuntimeConfig> {
     System(system::Event<T>),
     Balances(balances::Event<T>),
@@ -370,12 +406,39 @@ mod tests {
         assert!(runtime.take_events().is_empty());
     }
 
+    #[test]
+    fn transfer_overflowing_recipient_fails() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.genesis_config(vec![(alice.clone(), 10), (bob.clone(), Balance::MAX - 5)]);
+        runtime.take_events();
 
+        assert_eq!(runtime.execute_transfer(alice.clone(), bob.clone(), 6), Err(Error::Overflow));
+        assert_eq!(runtime.account_balance(&alice), 10);
+        assert_eq!(runtime.account_balance(&bob), Balance::MAX - 5);
+        assert_eq!(runtime.account_nonce(&alice), 0);
+        assert!(runtime.take_events().is_empty());
 
+        assert_eq!(runtime.execute_transfer(alice.clone(), bob.clone(), 5), Ok(()));
+        assert_eq!(runtime.account_balance(&bob), Balance::MAX);
+    }
 
+    #[test]
+    fn runtime_events_json_round_trip() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.genesis_config(vec![(alice.clone(), Balance::MAX)]);
+        runtime.execute_transfer(alice.clone(), bob.clone(), 250).unwrap();
+        let events = runtime.take_events();
 
+        let json = serde_json::to_string(&events).unwrap();
+        assert!(json.contains(&format!(r#"{{"Balances":{{"BalanceSet":{{"account":"alice","balance":"{}"}}}}}}"#, Balance::MAX)));
+        assert!(json.contains(r#"{"System":{"NewAccount":{"account":"alice"}}}"#));
+        assert!(json.contains(r#"{"Balances":{"Transfer":{"from":"alice","to":"bob","amount":"250"}}}"#));
 
-
-
-
-}
\ No newline at end of file
+        let decoded: Vec<RuntimeEvent<TestRuntimeConfig>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, events);
+    }
+}
@@ -8,6 +8,7 @@ mod challenge_05;
 mod challenge_06;
 mod challenge_07;
 mod challenge_08;
+mod challenge_09;
 mod challenge_10;
 mod challenge_11;
 mod challenge_12;
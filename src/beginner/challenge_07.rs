@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use std::ops::Add;
+use crate::common::arithmetic::{ArithmeticError, BalanceArithmetic};
 
 pub trait Summable {
     type Output;
@@ -11,16 +11,16 @@ pub struct Store<T> {
     value: T
 }
 
-impl<T: Clone + Add<Output = T> + Copy> Store<T> {
+impl<T: Clone + BalanceArithmetic + Copy> Store<T> {
     pub fn new(v: T) -> Self {
         Store{value: v}
     }
 }
 
-impl <T> Summable for Store<T> where T: Clone + Add<Output = T> + Copy, {
-    type Output = T;
-    fn sum_with(&self, other: &Self) -> T {
-        self.value + other.value
+impl <T> Summable for Store<T> where T: Clone + BalanceArithmetic + Copy, {
+    type Output = Result<T, ArithmeticError>;
+    fn sum_with(&self, other: &Self) -> Result<T, ArithmeticError> {
+        self.value.try_add(other.value)
     }
 }
 
@@ -33,7 +33,7 @@ mod tests {
         let a = Store::new(10u32);
         let b = Store::new(20u32);
         let result = a.sum_with(&b);
-        assert_eq!(result, 30u32);
+        assert_eq!(result, Ok(30u32));
     }
 
     #[test]
@@ -41,12 +41,20 @@ mod tests {
         let a = Store::new(-5i64);
         let b = Store::new(15i64);
         let result = a.sum_with(&b);
-        assert_eq!(result, 10i64);
+        assert_eq!(result, Ok(10i64));
+    }
+
+    #[test]
+    fn test_sum_with_overflow() {
+        let a = Store::new(u128::MAX);
+        assert_eq!(a.sum_with(&Store::new(0)), Ok(u128::MAX));
+        assert_eq!(a.sum_with(&Store::new(1)), Err(ArithmeticError::Overflow));
+        assert_eq!(Store::new(i64::MIN).sum_with(&Store::new(-1)), Err(ArithmeticError::Underflow));
     }
 }
 
 fn main() {
     let a = Store::new(30i64);
     let b = Store::new(5i64);
-    println!("{}", a.sum_with(&b));
+    println!("{:?}", a.sum_with(&b));
 } 
\ No newline at end of file
@@ -0,0 +1,96 @@
+//! Checked and saturating arithmetic for balance types.
+//!
+//! Pallets do balance math through `BalanceArithmetic` instead of `+`/`-`, so an
+//! overflow becomes an `ArithmeticError` (mapped into the pallet's own `Error`)
+//! rather than a debug-build panic or a silent wrap in release builds.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithmeticError {
+    /// The result is above the type's maximum.
+    Overflow,
+    /// The result is below the type's minimum, e.g. below zero for unsigned balances.
+    Underflow,
+}
+
+pub trait BalanceArithmetic: Copy {
+    fn try_add(self, rhs: Self) -> Result<Self, ArithmeticError>;
+    fn try_sub(self, rhs: Self) -> Result<Self, ArithmeticError>;
+    /// Clamps to the type's bounds instead of failing.
+    #[allow(dead_code)]
+    fn saturating_add(self, rhs: Self) -> Self;
+    #[allow(dead_code)]
+    fn saturating_sub(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {$(
+        impl BalanceArithmetic for $t {
+            fn try_add(self, rhs: Self) -> Result<Self, ArithmeticError> {
+                self.checked_add(rhs).ok_or(ArithmeticError::Overflow)
+            }
+
+            fn try_sub(self, rhs: Self) -> Result<Self, ArithmeticError> {
+                self.checked_sub(rhs).ok_or(ArithmeticError::Underflow)
+            }
+
+            fn saturating_add(self, rhs: Self) -> Self {
+                <$t>::saturating_add(self, rhs)
+            }
+
+            fn saturating_sub(self, rhs: Self) -> Self {
+                <$t>::saturating_sub(self, rhs)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed {
+    ($($t:ty),*) => {$(
+        impl BalanceArithmetic for $t {
+            fn try_add(self, rhs: Self) -> Result<Self, ArithmeticError> {
+                let error = if rhs > 0 {ArithmeticError::Overflow} else {ArithmeticError::Underflow};
+                self.checked_add(rhs).ok_or(error)
+            }
+
+            fn try_sub(self, rhs: Self) -> Result<Self, ArithmeticError> {
+                let error = if rhs > 0 {ArithmeticError::Underflow} else {ArithmeticError::Overflow};
+                self.checked_sub(rhs).ok_or(error)
+            }
+
+            fn saturating_add(self, rhs: Self) -> Self {
+                <$t>::saturating_add(self, rhs)
+            }
+
+            fn saturating_sub(self, rhs: Self) -> Self {
+                <$t>::saturating_sub(self, rhs)
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128);
+impl_signed!(i8, i16, i32, i64, i128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsigned_bounds() {
+        assert_eq!(BalanceArithmetic::try_add(u128::MAX - 1, 1), Ok(u128::MAX));
+        assert_eq!(BalanceArithmetic::try_add(u128::MAX, 1), Err(ArithmeticError::Overflow));
+        assert_eq!(BalanceArithmetic::try_sub(0u128, 1), Err(ArithmeticError::Underflow));
+        assert_eq!(BalanceArithmetic::saturating_add(u128::MAX, u128::MAX), u128::MAX);
+        assert_eq!(BalanceArithmetic::saturating_sub(1u128, 2), 0);
+    }
+
+    #[test]
+    fn signed_bounds() {
+        assert_eq!(BalanceArithmetic::try_add(i64::MAX, 1), Err(ArithmeticError::Overflow));
+        assert_eq!(BalanceArithmetic::try_add(i64::MIN, -1), Err(ArithmeticError::Underflow));
+        assert_eq!(BalanceArithmetic::try_sub(i64::MIN, 1), Err(ArithmeticError::Underflow));
+        assert_eq!(BalanceArithmetic::try_sub(i64::MAX, -1), Err(ArithmeticError::Overflow));
+        assert_eq!(BalanceArithmetic::try_add(-5i64, 15), Ok(10));
+        assert_eq!(BalanceArithmetic::saturating_sub(i64::MIN, 1), i64::MIN);
+    }
+}
@@ -0,0 +1,295 @@
+//! Minimal SCALE-style binary codec for the simulated runtime types.
+//!
+//! Layout rules, matching `parity-scale-codec`:
+//! - Fixed-width integers are little-endian.
+//! - Lengths and amounts marked compact use the SCALE compact encoding.
+//! - `String` and `Vec<T>` are a compact length followed by the items.
+//! - `Option<T>` is `0x00` for `None`, `0x01` followed by the value for `Some`.
+//! - Enums are a one-byte variant index followed by the variant's fields.
+//!
+//! Decoding is strict: non-canonical compact values, unknown tags and
+//! leftover bytes are errors rather than being silently accepted.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodecError {
+    /// The input ended before the value was complete.
+    UnexpectedEnd,
+    /// `decode_all` finished with this many bytes left over.
+    #[allow(dead_code)]
+    TrailingBytes(usize),
+    /// A versioned value started with a version this code does not understand.
+    UnknownVersion(u8),
+    /// An enum or `Option` tag that does not name any variant.
+    InvalidTag(u8),
+    /// A compact value that is out of range or not in its shortest form.
+    InvalidCompact,
+    InvalidUtf8,
+}
+
+pub trait Encode {
+    fn encode_to(&self, out: &mut Vec<u8>);
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_to(&mut out);
+        out
+    }
+}
+
+pub trait Decode: Sized {
+    /// Decodes a value from the front of `input`, advancing it past the consumed bytes.
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError>;
+
+    /// Decodes a value that must span exactly `bytes`.
+    #[allow(dead_code)]
+    fn decode_all(mut bytes: &[u8]) -> Result<Self, CodecError> {
+        let value = Self::decode(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(CodecError::TrailingBytes(bytes.len()));
+        }
+        Ok(value)
+    }
+}
+
+pub fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], CodecError> {
+    if input.len() < len {
+        return Err(CodecError::UnexpectedEnd);
+    }
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+    Ok(head)
+}
+
+pub fn read_byte(input: &mut &[u8]) -> Result<u8, CodecError> {
+    Ok(take(input, 1)?[0])
+}
+
+pub fn encode_compact(value: u128, out: &mut Vec<u8>) {
+    if value < 1 << 6 {
+        out.push((value as u8) << 2);
+    } else if value < 1 << 14 {
+        out.extend_from_slice(&(((value as u16) << 2) | 0b01).to_le_bytes());
+    } else if value < 1 << 30 {
+        out.extend_from_slice(&(((value as u32) << 2) | 0b10).to_le_bytes());
+    } else {
+        let bytes = value.to_le_bytes();
+        let len = 16 - value.leading_zeros() as usize / 8;
+        out.push((((len - 4) as u8) << 2) | 0b11);
+        out.extend_from_slice(&bytes[..len]);
+    }
+}
+
+pub fn decode_compact(input: &mut &[u8]) -> Result<u128, CodecError> {
+    let first = read_byte(input)?;
+    let value = match first & 0b11 {
+        0b00 => return Ok((first >> 2) as u128),
+        0b01 => {
+            let value = u16::from_le_bytes([first, read_byte(input)?]) >> 2;
+            (value >= 1 << 6).then_some(value as u128)
+        }
+        0b10 => {
+            let mut bytes = [first, 0, 0, 0];
+            bytes[1..].copy_from_slice(take(input, 3)?);
+            let value = u32::from_le_bytes(bytes) >> 2;
+            (value >= 1 << 14).then_some(value as u128)
+        }
+        _ => {
+            let len = (first >> 2) as usize + 4;
+            if len > 16 {
+                return Err(CodecError::InvalidCompact);
+            }
+            let mut bytes = [0u8; 16];
+            bytes[..len].copy_from_slice(take(input, len)?);
+            let value = u128::from_le_bytes(bytes);
+            (value >= 1 << 30 && bytes[len - 1] != 0).then_some(value)
+        }
+    };
+    value.ok_or(CodecError::InvalidCompact)
+}
+
+/// Compact-encoded collection length, rejected if it does not fit in `usize`.
+fn decode_len(input: &mut &[u8]) -> Result<usize, CodecError> {
+    usize::try_from(decode_compact(input)?).map_err(|_| CodecError::InvalidCompact)
+}
+
+impl Encode for u8 {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+impl Decode for u8 {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        read_byte(input)
+    }
+}
+
+impl Encode for u32 {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for u32 {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(take(input, 4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+}
+
+impl Encode for u64 {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for u64 {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(take(input, 8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+impl Encode for [u8; 32] {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+impl Decode for [u8; 32] {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(take(input, 32)?);
+        Ok(bytes)
+    }
+}
+
+impl Encode for str {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        encode_compact(self.len() as u128, out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.as_str().encode_to(out);
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        (**self).encode_to(out);
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = decode_len(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError::InvalidUtf8)
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        encode_compact(self.len() as u128, out);
+        for item in self {
+            item.encode_to(out);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = decode_len(input)?;
+        // Every item takes at least one byte, so a larger length is already truncated.
+        if len > input.len() {
+            return Err(CodecError::UnexpectedEnd);
+        }
+        (0..len).map(|_| T::decode(input)).collect()
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode_to(out);
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match read_byte(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            tag => Err(CodecError::InvalidTag(tag)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compact(value: u128) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_compact(value, &mut out);
+        out
+    }
+
+    #[test]
+    fn compact_matches_scale_vectors() {
+        assert_eq!(compact(0), vec![0x00]);
+        assert_eq!(compact(1), vec![0x04]);
+        assert_eq!(compact(63), vec![0xfc]);
+        assert_eq!(compact(64), vec![0x01, 0x01]);
+        assert_eq!(compact(16_383), vec![0xfd, 0xff]);
+        assert_eq!(compact(16_384), vec![0x02, 0x00, 0x01, 0x00]);
+        assert_eq!(compact((1 << 30) - 1), vec![0xfe, 0xff, 0xff, 0xff]);
+        assert_eq!(compact(1 << 30), vec![0x03, 0x00, 0x00, 0x00, 0x40]);
+        assert_eq!(compact(u128::MAX), [vec![0x33], vec![0xff; 16]].concat());
+
+        for value in [0, 63, 64, 16_383, 16_384, 1 << 30, u64::MAX as u128, u128::MAX] {
+            assert_eq!(decode_compact(&mut compact(value).as_slice()), Ok(value));
+        }
+    }
+
+    #[test]
+    fn compact_rejects_non_canonical_and_truncated_input() {
+        assert_eq!(decode_compact(&mut [0x01, 0x00].as_slice()), Err(CodecError::InvalidCompact));
+        assert_eq!(decode_compact(&mut [0x03, 0x01, 0x00, 0x00, 0x00].as_slice()), Err(CodecError::InvalidCompact));
+        assert_eq!(decode_compact(&mut [0x07, 0x00, 0x00, 0x00, 0x40, 0x00].as_slice()), Err(CodecError::InvalidCompact));
+        assert_eq!(decode_compact(&mut [0x37].as_slice()), Err(CodecError::InvalidCompact));
+        assert_eq!(decode_compact(&mut [0x02, 0x00].as_slice()), Err(CodecError::UnexpectedEnd));
+        assert_eq!(decode_compact(&mut [].as_slice()), Err(CodecError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn collections_round_trip_and_reject_leftovers() {
+        let value = (vec!["alice".to_string(), String::new()], Some(7u64), None::<u32>);
+        let mut bytes = Vec::new();
+        value.0.encode_to(&mut bytes);
+        value.1.encode_to(&mut bytes);
+        value.2.encode_to(&mut bytes);
+        assert_eq!(&bytes[..8], &[0x08, 0x14, b'a', b'l', b'i', b'c', b'e', 0x00]);
+
+        let mut input = bytes.as_slice();
+        assert_eq!(Vec::<String>::decode(&mut input), Ok(value.0));
+        assert_eq!(Option::<u64>::decode(&mut input), Ok(value.1));
+        assert_eq!(Option::<u32>::decode(&mut input), Ok(value.2));
+        assert!(input.is_empty());
+
+        assert_eq!(u32::decode_all(&[1, 0, 0, 0, 9]), Err(CodecError::TrailingBytes(1)));
+        assert_eq!(Option::<u8>::decode_all(&[2]), Err(CodecError::InvalidTag(2)));
+        assert_eq!(String::decode_all(&[0x04, 0xff]), Err(CodecError::InvalidUtf8));
+        assert_eq!(Vec::<u8>::decode_all(&[0x08, 0x01]), Err(CodecError::UnexpectedEnd));
+    }
+}
@@ -0,0 +1,133 @@
+//! BLAKE2b (RFC 7693), the hash Substrate uses for extrinsic and block hashes.
+//!
+//! Only the unkeyed variant is implemented; `blake2_256` matches
+//! `sp_core::hashing::blake2_256`.
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 12] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+];
+
+const BLOCK_SIZE: usize = 128;
+
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+fn compress(h: &mut [u64; 8], block: &[u8; BLOCK_SIZE], bytes_so_far: u128, last: bool) {
+    let mut m = [0u64; 16];
+    for (word, chunk) in m.iter_mut().zip(block.chunks_exact(8)) {
+        *word = u64::from_le_bytes(chunk.try_into().expect("chunks are 8 bytes"));
+    }
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= bytes_so_far as u64;
+    v[13] ^= (bytes_so_far >> 64) as u64;
+    if last {
+        v[14] = !v[14];
+    }
+
+    for s in &SIGMA {
+        mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+/// Unkeyed BLAKE2b with an `N`-byte digest, `1 <= N <= 64`.
+pub fn blake2b<const N: usize>(data: &[u8]) -> [u8; N] {
+    assert!((1..=64).contains(&N), "BLAKE2b digests are 1 to 64 bytes");
+    let mut h = IV;
+    h[0] ^= 0x0101_0000 ^ N as u64;
+
+    // The last block is compressed with the final flag, even when it is full or the input is empty.
+    let full_blocks = data.len().saturating_sub(1) / BLOCK_SIZE;
+    for (index, chunk) in data.chunks_exact(BLOCK_SIZE).take(full_blocks).enumerate() {
+        let block: &[u8; BLOCK_SIZE] = chunk.try_into().expect("chunks are one block");
+        compress(&mut h, block, ((index + 1) * BLOCK_SIZE) as u128, false);
+    }
+    let tail = &data[full_blocks * BLOCK_SIZE..];
+    let mut block = [0u8; BLOCK_SIZE];
+    block[..tail.len()].copy_from_slice(tail);
+    compress(&mut h, &block, data.len() as u128, true);
+
+    let mut bytes = [0u8; 64];
+    for (chunk, word) in bytes.chunks_exact_mut(8).zip(h) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    let mut out = [0u8; N];
+    out.copy_from_slice(&bytes[..N]);
+    out
+}
+
+pub fn blake2_256(data: &[u8]) -> [u8; 32] {
+    blake2b::<32>(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::serde_helpers::to_hex;
+
+    #[test]
+    fn blake2_256_known_answers() {
+        assert_eq!(to_hex(&blake2_256(b"")), "0x0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8");
+        assert_eq!(to_hex(&blake2_256(b"abc")), "0xbddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319");
+        assert_eq!(
+            to_hex(&blake2_256(b"The quick brown fox jumps over the lazy dog")),
+            "0x01718cec35cd3d796dd00020e0bfecb473ad23457d063b75eff29c0ffa2e58a9"
+        );
+        let multi_block: Vec<u8> = (0..200).collect();
+        assert_eq!(to_hex(&blake2_256(&multi_block)), "0x63c3d97a9f8894d5e043a707b0fee7f7ec4c049a23bbf1079df20b4165f9e22d");
+    }
+
+    #[test]
+    fn blake2_256_block_boundaries() {
+        let one_block: Vec<u8> = (0..128).collect();
+        let two_blocks: Vec<u8> = (0..=255).collect();
+        assert_eq!(to_hex(&blake2_256(&one_block)), "0xc3582f71ebb2be66fa5dd750f80baae97554f3b015663c8be377cfcb2488c1d1");
+        assert_eq!(to_hex(&blake2_256(&two_blocks)), "0x39a7eb9fedc19aabc83425c6755dd90e6f9d0c804964a1f4aaeea3b9fb599835");
+    }
+
+    #[test]
+    fn blake2b_512_known_answer() {
+        assert_eq!(
+            to_hex(&blake2b::<64>(b"abc")),
+            "0xba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+    }
+}
@@ -0,0 +1,10 @@
+// Shared helpers used across challenge levels.
+//
+// These modules hold small pieces of infrastructure that several simulated
+// pallets need, so each challenge does not have to re-implement them.
+
+pub mod arithmetic;
+pub mod codec;
+pub mod hashing;
+pub mod serde_helpers;
+pub mod signature;
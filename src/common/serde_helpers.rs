@@ -0,0 +1,136 @@
+//! Serde adapters for the simulated runtime types.
+//!
+//! JSON conventions used by every type that derives `Serialize`/`Deserialize`:
+//! - `[u8; 32]` hashes and raw byte blobs are `0x`-prefixed lowercase hex strings.
+//! - `u128` balances are decimal strings, because JSON numbers lose precision above 2^53.
+//! - Maps are written in key order so the same value always produces the same JSON.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(2 + bytes.len() * 2);
+    out.push_str("0x");
+    for byte in bytes {
+        out.push_str(&format!("{:02x}", byte));
+    }
+    out
+}
+
+pub fn from_hex(input: &str) -> Result<Vec<u8>, String> {
+    let digits = input.strip_prefix("0x").unwrap_or(input);
+    if !digits.is_ascii() || !digits.len().is_multiple_of(2) {
+        return Err(format!("malformed hex string {:?}", input));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| format!("invalid hex digit in {:?}", input))
+        })
+        .collect()
+}
+
+/// `[u8; 32]` as a `0x`-prefixed hex string.
+pub mod hex_hash {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(hash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let bytes = from_hex(&text).map_err(D::Error::custom)?;
+        bytes
+            .try_into()
+            .map_err(|bytes: Vec<u8>| D::Error::custom(format!("expected 32 bytes, got {}", bytes.len())))
+    }
+}
+
+/// `Vec<u8>` as a `0x`-prefixed hex string.
+pub mod hex_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        from_hex(&text).map_err(D::Error::custom)
+    }
+}
+
+/// `Vec<Vec<u8>>` as an array of `0x`-prefixed hex strings.
+pub mod hex_bytes_list {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(list: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(list.iter().map(|bytes| to_hex(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error> {
+        let raw = Vec::<String>::deserialize(deserializer)?;
+        raw.iter().map(|text| from_hex(text).map_err(D::Error::custom)).collect()
+    }
+}
+
+/// `HashMap<String, Vec<u8>>` as a key-ordered object of hex strings.
+pub mod hex_bytes_map {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+
+    pub fn serialize<S: Serializer>(map: &HashMap<String, Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        let ordered: BTreeMap<&String, String> = map.iter().map(|(key, value)| (key, to_hex(value))).collect();
+        serializer.collect_map(ordered)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, Vec<u8>>, D::Error> {
+        let raw = HashMap::<String, String>::deserialize(deserializer)?;
+        raw.into_iter()
+            .map(|(key, value)| from_hex(&value).map(|bytes| (key, bytes)).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+/// Any `Display + FromStr` value (e.g. `u128` balances) as a JSON string.
+pub mod as_string {
+    use super::*;
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trip() {
+        let bytes = vec![0x00, 0x01, 0xab, 0xff];
+        let encoded = to_hex(&bytes);
+        assert_eq!(encoded, "0x0001abff");
+        assert_eq!(from_hex(&encoded), Ok(bytes));
+    }
+
+    #[test]
+    fn from_hex_rejects_malformed_input() {
+        assert!(from_hex("0x123").is_err());
+        assert!(from_hex("0xzz").is_err());
+        assert_eq!(from_hex("0x"), Ok(vec![]));
+    }
+}
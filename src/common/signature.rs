@@ -0,0 +1,161 @@
+//! Deterministic Schnorr signatures for tests and simulations.
+//!
+//! Works in the multiplicative group modulo the Mersenne prime 2^61 - 1, which
+//! is far too small to be secure but keeps the scheme dependency-free and fast.
+//! Signing nonces are derived from the secret and the message, so the same key
+//! always produces the same signature for the same message.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::common::hashing::blake2_256;
+use crate::common::serde_helpers::{from_hex, to_hex};
+
+const MODULUS: u64 = (1 << 61) - 1;
+/// Exponents are reduced modulo the group order.
+const ORDER: u64 = MODULUS - 1;
+const GENERATOR: u64 = 37;
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    ((a as u128 * b as u128) % modulus as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64) -> u64 {
+    let mut result = 1;
+    base %= MODULUS;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, MODULUS);
+        }
+        base = mul_mod(base, base, MODULUS);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Hashes `parts` into a non-zero exponent.
+fn scalar(parts: &[&[u8]]) -> u64 {
+    let hash = blake2_256(&parts.concat());
+    let value = u64::from_le_bytes(hash[..8].try_into().expect("hash is 32 bytes")) % ORDER;
+    value.max(1)
+}
+
+pub struct Keypair {
+    secret: u64,
+    public: PublicKey,
+}
+
+impl Keypair {
+    /// Derives a keypair from `seed`, e.g. `b"alice"`.
+    #[allow(dead_code)]
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let secret = scalar(&[b"secret", seed]);
+        Self { secret, public: PublicKey(pow_mod(GENERATOR, secret)) }
+    }
+
+    pub fn public(&self) -> PublicKey {
+        self.public
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let nonce = scalar(&[&self.secret.to_le_bytes(), message]);
+        let r = pow_mod(GENERATOR, nonce);
+        let challenge = challenge(r, self.public, message);
+        let s = ((nonce as u128 + mul_mod(challenge, self.secret, ORDER) as u128) % ORDER as u128) as u64;
+        Signature { r, s }
+    }
+}
+
+fn challenge(r: u64, public: PublicKey, message: &[u8]) -> u64 {
+    scalar(&[&r.to_le_bytes(), &public.0.to_le_bytes(), message])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PublicKey(u64);
+
+impl PublicKey {
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+        if signature.r == 0 || signature.r >= MODULUS || signature.s >= ORDER {
+            return false;
+        }
+        let challenge = challenge(signature.r, *self, message);
+        pow_mod(GENERATOR, signature.s) == mul_mod(signature.r, pow_mod(self.0, challenge), MODULUS)
+    }
+}
+
+/// JSON: the 16 bytes of `to_bytes` as a `0x`-prefixed hex string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    r: u64,
+    s: u64,
+}
+
+impl Signature {
+    /// `r` then `s`, each little-endian.
+    pub fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.r.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.s.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; 16]) -> Self {
+        Self {
+            r: u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes")),
+            s: u64::from_le_bytes(bytes[8..].try_into().expect("8 bytes")),
+        }
+    }
+}
+
+impl Serialize for Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(&self.to_bytes()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = from_hex(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)?;
+        let bytes: [u8; 16] = bytes
+            .try_into()
+            .map_err(|bytes: Vec<u8>| serde::de::Error::custom(format!("expected 16 bytes, got {}", bytes.len())))?;
+        Ok(Self::from_bytes(&bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_verify_only_for_the_signed_message_and_key() {
+        let alice = Keypair::from_seed(b"alice");
+        let bob = Keypair::from_seed(b"bob");
+        let signature = alice.sign(b"transfer 10");
+
+        assert!(alice.public().verify(b"transfer 10", &signature));
+        assert!(!alice.public().verify(b"transfer 11", &signature));
+        assert!(!bob.public().verify(b"transfer 10", &signature));
+        assert!(!alice.public().verify(b"transfer 10", &bob.sign(b"transfer 10")));
+
+        let mut tampered = signature.to_bytes();
+        tampered[8] ^= 1;
+        assert!(!alice.public().verify(b"transfer 10", &Signature::from_bytes(&tampered)));
+    }
+
+    #[test]
+    fn keys_and_signatures_are_deterministic() {
+        assert_eq!(Keypair::from_seed(b"alice").public(), Keypair::from_seed(b"alice").public());
+        assert_ne!(Keypair::from_seed(b"alice").public(), Keypair::from_seed(b"bob").public());
+        let alice = Keypair::from_seed(b"alice");
+        assert_eq!(alice.sign(b"message"), alice.sign(b"message"));
+        assert_ne!(alice.sign(b"message"), alice.sign(b"other message"));
+    }
+
+    #[test]
+    fn signature_json_round_trip() {
+        let signature = Keypair::from_seed(b"alice").sign(b"message");
+        let json = serde_json::to_string(&signature).unwrap();
+        assert_eq!(json.len(), 2 + 2 + 32);
+        assert_eq!(serde_json::from_str::<Signature>(&json).unwrap(), signature);
+        assert!(serde_json::from_str::<Signature>("\"0x00\"").is_err());
+    }
+}
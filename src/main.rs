@@ -2,6 +2,7 @@
 mod beginner;
 mod medium;
 mod advanced;
+mod common;
 
 fn main() {
     println!("Hello, world!");